@@ -1317,6 +1317,24 @@ create_effect(move || {
 // When count changes, the output will be:
 // Effect 1: 1
 // Effect 2: 1"#}
+                />
+                <h3>Cleaning Up</h3>
+                <p>"An effect can return a closure, which runs before the effect runs again and when its component goes away:"</p>
+                <CodeBlock
+                    language="rust"
+                    filename="src/main.rs"
+                    highlight=""
+                    code={r#"// Effect with cleanup
+let user_id = create_signal(1);
+
+create_effect(move || {
+    let id = user_id.get();
+    log!("Subscribed to user {}", id);
+
+    move || {
+        log!("Unsubscribed from user {}", id);
+    }
+});"#}
                 />
                 <h2 class="font-bold uppercase">Best Practices</h2>
                 <ul>
@@ -1435,6 +1453,10 @@ count.set(5); // Now count is 5
 
 // Update based on previous value
 count += 1; // Now count is 6
+
+// Mutate in place, without copying the value
+let items = create_signal(vec![1, 2, 3]);
+items.update(|items| items.retain(|item| item % 2 == 1));
 "#}
                 />
                 <Note variant="tip">
@@ -1,6 +1,6 @@
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::{ToTokens, quote, quote_spanned};
+use quote::{ToTokens, format_ident, quote, quote_spanned};
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::token::Colon;
@@ -485,17 +485,149 @@ pub fn component(_attr: TokenStream, input: TokenStream) -> TokenStream {
     expanded.into()
 }
 
+/// Kept for backwards compatibility, as every `Send` type that doesn't borrow
+/// anything can be stored in a signal as is. Expands to nothing.
+///
+/// `SignalValue` is implemented for all of those types, so manual
+/// implementations of it no longer compile and should be deleted along with
+/// this derive.
 #[proc_macro_derive(SignalValue)]
-pub fn derive_signal_value(input: TokenStream) -> TokenStream {
+pub fn derive_signal_value(_input: TokenStream) -> TokenStream {
+    TokenStream::new()
+}
+
+/// Derives a store for a struct, which keeps each field in its own signal so
+/// that scopes only depend on the fields they read.
+///
+/// For a struct `AppState`, this generates an `AppStateStore` handle, created
+/// with `create_store`, with an accessor per field returning the field's
+/// signal. Fields marked `#[store]` hold a struct deriving `Store` themselves
+/// and return its handle instead, so nested fields can be reached as
+/// `store.user().name()`. Every other field is a signal of its own, and the
+/// items of a `Vec` field can be read through a lens with
+/// `store.todos().at(index)`, which only notifies when that item changes.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[derive(Store)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[derive(Store)]
+/// struct AppState {
+///     #[store]
+///     user: User,
+///     todos: Vec<String>,
+/// }
+///
+/// #[component]
+/// fn App() -> Node {
+///     let store = create_store(AppState {
+///         user: User { name: "Ada".to_string() },
+///         todos: Vec::new(),
+///     });
+///     store.todos().push("Write docs".to_string());
+///     let first = store.todos().at(0);
+///     // only renders again when the name or the first todo changes
+///     rsx!(<p>{store.user().name()} {move || first.get().unwrap_or_default()}</p>)
+/// }
+/// ```
+#[proc_macro_derive(Store, attributes(store))]
+pub fn derive_store(input: TokenStream) -> TokenStream {
     let syn::DeriveInput {
-        ident, generics, ..
+        vis,
+        ident,
+        generics,
+        data,
+        ..
     } = syn::parse_macro_input!(input as syn::DeriveInput);
+    let fields = match data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => fields.named,
+        _ => {
+            return syn::Error::new(
+                ident.span(),
+                "Store can only be derived for structs with named fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let store_ident = format_ident!("{}Store", ident);
+
+    let mut handle_fields = Vec::new();
+    let mut accessors = Vec::new();
+    let mut inits = Vec::new();
+    let mut sets = Vec::new();
+    for field in &fields {
+        let syn::Field {
+            vis: field_vis,
+            ident: field_ident,
+            ty,
+            attrs,
+            ..
+        } = field;
+        let nested = attrs.iter().any(|attr| attr.path().is_ident("store"));
+        let (handle_ty, init) = if nested {
+            (
+                quote!(<#ty as ::momenta::signals::Store>::Handle),
+                quote!(::momenta::signals::Store::into_handle(self.#field_ident)),
+            )
+        } else {
+            (
+                quote!(::momenta::signals::Signal<#ty>),
+                quote!(::momenta::signals::create_signal(self.#field_ident)),
+            )
+        };
+        handle_fields.push(quote!(#field_ident: #handle_ty));
+        accessors.push(quote! {
+            #field_vis fn #field_ident(&self) -> #handle_ty {
+                self.#field_ident
+            }
+        });
+        inits.push(quote!(#field_ident: #init));
+        sets.push(quote!(self.#field_ident.set(value.#field_ident);));
+    }
 
     let expanded = quote! {
-        impl #impl_generics ::momenta::signals::SignalValue for #ident #ty_generics #where_clause {
-            fn as_any(&self) -> Option<&dyn core::any::Any> {
-                Some(self)
+        #vis struct #store_ident #impl_generics #where_clause {
+            #(#handle_fields,)*
+        }
+
+        impl #impl_generics #store_ident #ty_generics #where_clause {
+            #(#accessors)*
+
+            /// Replaces every field, only notifying the readers of the ones that changed
+            #vis fn set(&self, value: #ident #ty_generics) {
+                ::momenta::signals::batch(|| {
+                    #(#sets)*
+                });
+            }
+        }
+
+        impl #impl_generics Clone for #store_ident #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #impl_generics Copy for #store_ident #ty_generics #where_clause {}
+
+        impl #impl_generics ::momenta::signals::Store for #ident #ty_generics #where_clause {
+            type Handle = #store_ident #ty_generics;
+
+            #[track_caller]
+            fn into_handle(self) -> Self::Handle {
+                #store_ident {
+                    #(#inits,)*
+                }
             }
         }
     };
@@ -530,6 +662,21 @@ pub fn derive_signal_value(input: TokenStream) -> TokenStream {
 /// // Keyword attributes (automatically converted with _ suffix)
 /// rsx!(<input type="text" for="name" />);
 /// ```
+///
+/// Components keep their state when the surrounding component renders again,
+/// and only render again themselves if their props changed, as far as their
+/// `PartialEq` implementation can tell. Props without one always count as changed.
+///
+/// On components, `key` is reserved: it is not passed as a prop but used to keep
+/// the component's state attached to the same item when a list is reordered.
+///
+/// Closures without arguments, as children (`{move || count.get()}`) or element
+/// attributes (`title={move || ...}`), are re-evaluated on their own when the
+/// signals they read change, without re-rendering the surrounding component.
+///
+/// `<For each={items} key={|item| ...}>{|item| ...}</For>` renders a row for each
+/// item of a `SignalVec`, inserting, removing or moving only the rows of the
+/// items that changed. Here `key` tells the rows apart.
 
 #[proc_macro]
 pub fn rsx(input: TokenStream) -> TokenStream {
@@ -859,6 +1006,29 @@ impl RsxNode {
             } => {
                 let is_element = name.to_string().starts_with(|c: char| !c.is_uppercase());
 
+                // `<For>` takes a closure rendering each row as its child, and its
+                // `key` tells rows apart rather than the `For` itself
+                if name == "For" {
+                    let prop = |prop: &str| {
+                        props
+                            .iter()
+                            .find(|(name, _, _)| name.as_ref().is_some_and(|name| name == prop))
+                            .and_then(|(_, value, _)| value.as_ref())
+                    };
+                    let (Some(each), Some(key), [RsxNode::Text(row)]) =
+                        (prop("each"), prop("key"), children.as_slice())
+                    else {
+                        return syn::Error::new(
+                            *open_span,
+                            "<For> takes `each` and `key` props, and a closure rendering each item as its only child",
+                        )
+                        .to_compile_error();
+                    };
+                    return quote_spanned! { *open_span=>
+                        #name::each(#each, #key, #row)
+                    };
+                }
+
                 let attrs = props
                     .iter() // filter out data- attributes for elements
                     .map(|(name, value, span)| {
@@ -913,7 +1083,49 @@ impl RsxNode {
                     }
                 });
 
+                // closures without arguments bind element attributes to the signals they read
+                let is_binding = |name: &Option<Ident>, value: &Option<Expr>| {
+                    is_element
+                        && name.as_ref().is_some_and(|name| {
+                            let name = name.to_string();
+                            !name.starts_with("on_") && !name.starts_with("data_")
+                        })
+                        && matches!(value, Some(Expr::Closure(closure)) if closure.inputs.is_empty())
+                };
+                let bindings = props
+                    .iter()
+                    .filter(|(name, value, _)| is_binding(name, value))
+                    .map(|(name, value, span)| {
+                        let name = name
+                            .as_ref()
+                            .map(|name| name.to_string())
+                            .unwrap_or_default();
+                        let name = name.strip_suffix('_').unwrap_or(&name).replace('_', "-");
+                        quote_spanned! {*span=>
+                            (#name.to_string(), ::momenta::nodes::DynamicAttribute::new(#value))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let dynamic_props = (!bindings.is_empty()).then(|| {
+                    quote_spanned! { *open_span=>
+                        dynamic_: vec![#(#bindings),*],
+                    }
+                });
+
+                // components take `key` for reconciliation rather than as a prop
+                let is_key = |name: &Option<Ident>| {
+                    !is_element && name.as_ref().is_some_and(|name| name == "key")
+                };
+                let key = attrs
+                    .clone()
+                    .find(|(name, _, _)| is_key(name))
+                    .map(|(_, value, span)| quote_spanned! {span=> {#value}.into() });
+
                 let props_tokens = attrs
+                    .zip(props)
+                    .filter(|(_, (name, value, _))| !is_binding(name, value))
+                    .map(|(attr, _)| attr)
+                    .filter(|(name, _, _)| !is_key(name))
                     .filter(|(name, _, _)| {
                         !(is_element
                             // filter out data- attributes for elements
@@ -964,19 +1176,43 @@ impl RsxNode {
                     quote_spanned! { *open_span=> momenta::dom::elements::#name }
                 };
 
+                let props = quote_spanned! { *open_span=>
+                    Props {
+                        #(#props_tokens)*
+                        #children_tokens
+                        #data_props
+                        #dynamic_props
+                        #default_props
+                    }
+                };
+
+                // elements are pure functions of their props, so only components get a scope
+                let render = if is_element {
+                    quote_spanned! { *open_span=>
+                        <#component as ::momenta::nodes::Component>::render(&#props)
+                    }
+                } else {
+                    let key = match key {
+                        Some(key) => quote_spanned! { *open_span=> Some(#key) },
+                        None => quote_spanned! { *open_span=> None },
+                    };
+                    // props are compared with `PartialEq` when they implement it,
+                    // otherwise the component renders again with its parent
+                    quote_spanned! { *open_span=>
+                        ::momenta::dom::child_component::<#component>(#key, #props, |old, new| {
+                            #[allow(unused_imports)]
+                            use ::momenta::dom::{ComparableProps as _, OpaqueProps as _};
+                            (&::momenta::dom::PropsChange(old, new)).changed()
+                        })
+                    }
+                };
+
                 quote_spanned! { *open_span=>
                     {
                         type Props = <#component as ::momenta::nodes::Component>::Props;
                         {
                             #close_tag_validation
-                            ::momenta::dom::component::<#component>(
-                                Props {
-                                    #(#props_tokens)*
-                                    #children_tokens
-                                    #data_props
-                                    #default_props
-                                }
-                            )
+                            #render
                         }
                     }
                 }
@@ -0,0 +1,69 @@
+//! Measures what reading and writing a signal costs, next to the dynamic type
+//! check each access makes on the value it stores.
+//!
+//! Run with `cargo bench -p momenta`.
+
+use momenta::prelude::*;
+use std::{
+    any::Any,
+    cell::RefCell,
+    hint::black_box,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn measure(name: &str, mut f: impl FnMut(u32)) {
+    // warm up caches and the arena before timing
+    for i in 0..ITERATIONS / 10 {
+        f(i);
+    }
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i);
+    }
+    let elapsed: Duration = start.elapsed();
+    println!(
+        "{name:<32} {:>8.2} ns/op",
+        elapsed.as_nanos() as f64 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    let stored: Rc<dyn Any> = Rc::new(0u64);
+    measure("downcast Rc<dyn Any>", |_| {
+        black_box(black_box(&stored).downcast_ref::<u64>());
+    });
+
+    let cell = Rc::new(RefCell::new(0u64));
+    measure("read Rc<RefCell<u64>>", |_| {
+        black_box(*black_box(&cell).borrow());
+    });
+
+    let (_root, count) = create_root(|| create_signal(0u64));
+    measure("Signal::with (untracked)", |_| {
+        untrack(|| black_box(count.with(|count| *count)));
+    });
+    let (root, _) = create_root(|| ());
+    root.run(|| {
+        measure("Signal::with (tracked)", |_| {
+            black_box(count.with(|count| *count));
+        })
+    });
+    measure("Signal::set", |i| count.set(i as u64));
+
+    // a table of rows each reading its own signal, as when rendering one
+    let (_root, rows) = create_root(|| (0..5_000u64).map(create_signal).collect::<Vec<_>>());
+    let start = Instant::now();
+    for _ in 0..100 {
+        for row in &rows {
+            black_box(row.get());
+        }
+    }
+    println!(
+        "{:<32} {:>8.2} us/pass",
+        "read 5k row signals",
+        start.elapsed().as_micros() as f64 / 100.0
+    );
+}
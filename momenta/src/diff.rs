@@ -0,0 +1,675 @@
+//! Virtual DOM diffing
+//!
+//! Instead of throwing away the DOM of a component every time it re-renders, the
+//! renderer compares the previous [`Node`] tree with the new one and only applies
+//! the differences as a list of [`Patch`]es.
+//!
+//! Patches address nodes by *path*: a list of child indices starting from the
+//! list of top-level nodes being diffed. Fragments, components, dynamic nodes
+//! and empty nodes produce no DOM nodes of their own, so paths are computed over
+//! flattened child lists and map one-to-one onto DOM child nodes.
+//!
+//! Patches are meant to be applied in order: every path is valid against the
+//! tree as it looks after all previous patches have been applied.
+//!
+//! # Example
+//!
+//! ```rust
+//! use momenta::prelude::*;
+//! use momenta::diff::Patch;
+//!
+//! let old = rsx!(<p class="old">Hello</p>);
+//! let new = rsx!(<p class="new">Hello</p>);
+//!
+//! let patches = Node::diff(&old, &new);
+//! assert!(matches!(
+//!     &patches[..],
+//!     [Patch::SetAttribute { name, value, .. }] if name == "class" && value == "new"
+//! ));
+//! ```
+
+use crate::nodes::{Element, Node};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+#[cfg(feature = "wasm")]
+use crate::nodes::EventCallback;
+
+/// A single change to apply to a rendered tree
+#[derive(Clone)]
+pub enum Patch {
+    /// Replace the node at `path` with a new node
+    Replace { path: Vec<usize>, node: Node },
+    /// Insert a node as the `index`-th child of the node at `parent`
+    Insert {
+        parent: Vec<usize>,
+        index: usize,
+        node: Node,
+    },
+    /// Remove the `index`-th child of the node at `parent`
+    Remove { parent: Vec<usize>, index: usize },
+    /// Move `count` children of the node at `parent`, starting at `from`, so
+    /// that they start at `to` among the children left once they are taken out
+    Move {
+        parent: Vec<usize>,
+        from: usize,
+        count: usize,
+        to: usize,
+    },
+    /// Update the content of the text or comment node at `path`
+    SetText { path: Vec<usize>, text: String },
+    /// Add or update an attribute of the element at `path`
+    SetAttribute {
+        path: Vec<usize>,
+        name: String,
+        value: String,
+    },
+    /// Remove an attribute from the element at `path`
+    RemoveAttribute { path: Vec<usize>, name: String },
+    #[cfg(feature = "wasm")]
+    /// Attach (or replace) an event handler on the element at `path`
+    SetEvent {
+        path: Vec<usize>,
+        name: String,
+        callback: EventCallback,
+    },
+    #[cfg(feature = "wasm")]
+    /// Detach an event handler from the element at `path`
+    RemoveEvent { path: Vec<usize>, name: String },
+}
+
+impl Node {
+    /// Computes the patches needed to turn `old` into `new`.
+    ///
+    /// Both nodes are treated as lists of top-level nodes, so a path's first
+    /// index is the position within the flattened `new` node.
+    ///
+    /// Children with a `key` are matched by key rather than by position, and
+    /// moved with as few [`Patch::Move`]s as possible when reordered.
+    pub fn diff(old: &Node, new: &Node) -> Vec<Patch> {
+        let mut patches = Vec::new();
+        let (mut old_items, mut new_items) = (Vec::new(), Vec::new());
+        collect_items(old, &mut old_items);
+        collect_items(new, &mut new_items);
+        diff_list(&old_items, &new_items, &mut Vec::new(), 0, &mut patches);
+        patches
+    }
+}
+
+/// A run of DOM nodes that is matched as a whole when diffing a child list
+struct Item<'a> {
+    key: Option<&'a str>,
+    node: &'a Node,
+    /// The number of DOM nodes the item renders to
+    len: usize,
+}
+
+/// Collects the units a child list is diffed by, in document order.
+///
+/// Fragments and unkeyed components are transparent, while keyed components
+/// are kept whole so that all their DOM nodes move together.
+fn collect_items<'a>(node: &'a Node, out: &mut Vec<Item<'a>>) {
+    match node {
+        Node::Fragment(nodes) => nodes.iter().for_each(|node| collect_items(node, out)),
+        Node::Component(component) if component.key().is_empty() => {
+            collect_items(component.node(), out)
+        }
+        Node::Dynamic(dynamic) => collect_items(dynamic.node(), out),
+        Node::Component(component) => out.push(Item {
+            key: Some(component.key()),
+            node,
+            len: dom_len(component.node()),
+        }),
+        Node::Element(el) if !el.key().is_empty() => out.push(Item {
+            key: Some(el.key()),
+            node,
+            len: 1,
+        }),
+        Node::Empty => {}
+        _ => out.push(Item {
+            key: None,
+            node,
+            len: 1,
+        }),
+    }
+}
+
+/// Counts the DOM nodes a node renders to
+fn dom_len(node: &Node) -> usize {
+    match node {
+        Node::Fragment(nodes) => nodes.iter().map(dom_len).sum(),
+        Node::Component(component) => dom_len(component.node()),
+        Node::Dynamic(dynamic) => dom_len(dynamic.node()),
+        Node::Empty => 0,
+        _ => 1,
+    }
+}
+
+/// Collects the nodes that map to actual DOM nodes, in document order
+fn flatten<'a>(node: &'a Node, out: &mut Vec<&'a Node>) {
+    match node {
+        Node::Fragment(nodes) => nodes.iter().for_each(|node| flatten(node, out)),
+        Node::Component(component) => flatten(component.node(), out),
+        Node::Dynamic(dynamic) => flatten(dynamic.node(), out),
+        Node::Empty => {}
+        _ => out.push(node),
+    }
+}
+
+/// Diffs two child lists of the node at `parent`, starting at DOM index `offset`
+fn diff_list(
+    old: &[Item],
+    new: &[Item],
+    parent: &mut Vec<usize>,
+    offset: usize,
+    patches: &mut Vec<Patch>,
+) {
+    if old.iter().chain(new).any(|item| item.key.is_some()) {
+        return diff_keyed(old, new, parent, offset, patches);
+    }
+
+    // unkeyed items are always single DOM nodes
+    let common = old.len().min(new.len());
+    for index in 0..common {
+        parent.push(offset + index);
+        diff_node(old[index].node, new[index].node, parent, patches);
+        parent.pop();
+    }
+    // remove from the back so the remaining indices stay valid
+    for index in (common..old.len()).rev() {
+        patches.push(Patch::Remove {
+            parent: parent.clone(),
+            index: offset + index,
+        });
+    }
+    for (index, item) in new.iter().enumerate().skip(common) {
+        patches.push(Patch::Insert {
+            parent: parent.clone(),
+            index: offset + index,
+            node: item.node.clone(),
+        });
+    }
+}
+
+fn diff_keyed(
+    old: &[Item],
+    new: &[Item],
+    parent: &mut Vec<usize>,
+    offset: usize,
+    patches: &mut Vec<Patch>,
+) {
+    // match new items to old ones: keyed items by key, unkeyed ones in order
+    let mut keyed = BTreeMap::new();
+    let mut unkeyed = Vec::new();
+    for (index, item) in old.iter().enumerate().rev() {
+        match item.key {
+            Some(key) => {
+                keyed.insert(key, index);
+            }
+            None => unkeyed.push(index),
+        }
+    }
+    let mut used = vec![false; old.len()];
+    let sources: Vec<Option<usize>> = new
+        .iter()
+        .map(|item| {
+            let source = match item.key {
+                Some(key) => keyed.remove(key),
+                None => unkeyed.pop(),
+            };
+            if let Some(index) = source {
+                used[index] = true;
+            }
+            source
+        })
+        .collect();
+
+    // drop unmatched items, back to front so earlier positions stay valid
+    let mut start = offset + old.iter().map(|item| item.len).sum::<usize>();
+    for (index, item) in old.iter().enumerate().rev() {
+        start -= item.len;
+        if !used[index] {
+            for index in (start..start + item.len).rev() {
+                patches.push(Patch::Remove {
+                    parent: parent.clone(),
+                    index,
+                });
+            }
+        }
+    }
+
+    // place items from the back, each right before its successor; items on the
+    // longest increasing run of old positions already are in order and stay put
+    let stable = longest_increasing(&sources);
+    let mut targets = vec![0; old.len()];
+    for (index, source) in sources.iter().enumerate() {
+        if let Some(source) = source {
+            targets[*source] = index;
+        }
+    }
+    // (new index, DOM length) of each item, in current DOM order
+    let mut current: Vec<(usize, usize)> = (0..old.len())
+        .filter(|index| used[*index])
+        .map(|index| (targets[index], old[index].len))
+        .collect();
+    let position = |current: &[(usize, usize)], index: usize| {
+        current.iter().position(|(target, _)| *target == index)
+    };
+    let dom_index = |current: &[(usize, usize)], position: usize| {
+        offset
+            + current[..position]
+                .iter()
+                .map(|(_, len)| len)
+                .sum::<usize>()
+    };
+    let anchor = |current: &[(usize, usize)], index: usize| {
+        position(current, index + 1).unwrap_or(current.len())
+    };
+    for (index, item) in new.iter().enumerate().rev() {
+        match sources[index] {
+            None => {
+                let at = anchor(&current, index);
+                let mut nodes = Vec::new();
+                flatten(item.node, &mut nodes);
+                let start = dom_index(&current, at);
+                for (i, node) in nodes.into_iter().enumerate() {
+                    patches.push(Patch::Insert {
+                        parent: parent.clone(),
+                        index: start + i,
+                        node: node.clone(),
+                    });
+                }
+                current.insert(at, (index, item.len));
+            }
+            Some(_) if !stable[index] => {
+                let Some(from) = position(&current, index) else {
+                    continue;
+                };
+                let from_index = dom_index(&current, from);
+                let (_, len) = current.remove(from);
+                let at = anchor(&current, index);
+                if len > 0 {
+                    patches.push(Patch::Move {
+                        parent: parent.clone(),
+                        from: from_index,
+                        count: len,
+                        to: dom_index(&current, at),
+                    });
+                }
+                current.insert(at, (index, len));
+            }
+            Some(_) => {}
+        }
+    }
+
+    // everything is in place, so update matched items from the front
+    let mut start = offset;
+    for (index, item) in new.iter().enumerate() {
+        if let Some(source) = sources[index] {
+            diff_item(&old[source], item, parent, start, patches);
+        }
+        start += item.len;
+    }
+}
+
+/// Diffs two matched items, the old one starting at DOM index `start`
+fn diff_item(
+    old: &Item,
+    new: &Item,
+    parent: &mut Vec<usize>,
+    start: usize,
+    patches: &mut Vec<Patch>,
+) {
+    match (old.node, new.node) {
+        (Node::Component(old), Node::Component(new)) => {
+            let (mut old_items, mut new_items) = (Vec::new(), Vec::new());
+            collect_items(old.node(), &mut old_items);
+            collect_items(new.node(), &mut new_items);
+            diff_list(&old_items, &new_items, parent, start, patches);
+        }
+        (Node::Component(_), _) | (_, Node::Component(_)) => {
+            for index in (start..start + old.len).rev() {
+                patches.push(Patch::Remove {
+                    parent: parent.clone(),
+                    index,
+                });
+            }
+            let mut nodes = Vec::new();
+            flatten(new.node, &mut nodes);
+            for (i, node) in nodes.into_iter().enumerate() {
+                patches.push(Patch::Insert {
+                    parent: parent.clone(),
+                    index: start + i,
+                    node: node.clone(),
+                });
+            }
+        }
+        _ => {
+            parent.push(start);
+            diff_node(old.node, new.node, parent, patches);
+            parent.pop();
+        }
+    }
+}
+
+/// Marks the items whose old positions form a longest increasing subsequence
+fn longest_increasing(sources: &[Option<usize>]) -> Vec<bool> {
+    // tails[n] is the item ending the smallest-valued increasing run of length n + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; sources.len()];
+    for (index, source) in sources.iter().enumerate() {
+        let Some(value) = source else {
+            continue;
+        };
+        let len = tails.partition_point(|tail| sources[*tail] < Some(*value));
+        if len > 0 {
+            previous[index] = Some(tails[len - 1]);
+        }
+        if len == tails.len() {
+            tails.push(index);
+        } else {
+            tails[len] = index;
+        }
+    }
+
+    let mut stable = vec![false; sources.len()];
+    let mut next = tails.last().copied();
+    while let Some(index) = next {
+        stable[index] = true;
+        next = previous[index];
+    }
+    stable
+}
+
+fn diff_node(old: &Node, new: &Node, path: &mut Vec<usize>, patches: &mut Vec<Patch>) {
+    match (old, new) {
+        (Node::Text(old), Node::Text(new)) | (Node::Comment(old), Node::Comment(new)) => {
+            if old != new {
+                patches.push(Patch::SetText {
+                    path: path.clone(),
+                    text: new.clone(),
+                });
+            }
+        }
+        (Node::Element(old), Node::Element(new))
+            if old.tag() == new.tag() && old.key() == new.key() && old.html() == new.html() =>
+        {
+            diff_element(old, new, path, patches);
+        }
+        _ => patches.push(Patch::Replace {
+            path: path.clone(),
+            node: new.clone(),
+        }),
+    }
+}
+
+fn diff_element(old: &Element, new: &Element, path: &mut Vec<usize>, patches: &mut Vec<Patch>) {
+    for (name, value) in new.attributes() {
+        if old.attributes().get(name) != Some(value) {
+            patches.push(Patch::SetAttribute {
+                path: path.clone(),
+                name: name.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+    for name in old.attributes().keys() {
+        if !new.attributes().contains_key(name) {
+            patches.push(Patch::RemoveAttribute {
+                path: path.clone(),
+                name: name.clone(),
+            });
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    {
+        for (name, callback) in new.events() {
+            // closures can't be compared, so only identical handlers are skipped
+            if !old
+                .events()
+                .get(name)
+                .is_some_and(|old| old.ptr_eq(callback))
+            {
+                patches.push(Patch::SetEvent {
+                    path: path.clone(),
+                    name: name.clone(),
+                    callback: callback.clone(),
+                });
+            }
+        }
+        for name in old.events().keys() {
+            if !new.events().contains_key(name) {
+                patches.push(Patch::RemoveEvent {
+                    path: path.clone(),
+                    name: name.clone(),
+                });
+            }
+        }
+    }
+
+    // children of raw html elements are owned by the browser
+    if new.html().is_empty() {
+        let (mut old_children, mut new_children) = (Vec::new(), Vec::new());
+        old.children()
+            .iter()
+            .for_each(|child| collect_items(child, &mut old_children));
+        new.children()
+            .iter()
+            .for_each(|child| collect_items(child, &mut new_children));
+        diff_list(&old_children, &new_children, path, 0, patches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use alloc::{boxed::Box, string::ToString, vec};
+
+    fn paths(patches: &[Patch]) -> Vec<Vec<usize>> {
+        patches
+            .iter()
+            .map(|patch| match patch {
+                Patch::Replace { path, .. }
+                | Patch::SetText { path, .. }
+                | Patch::SetAttribute { path, .. }
+                | Patch::RemoveAttribute { path, .. } => path.clone(),
+                Patch::Insert { parent, index, .. }
+                | Patch::Remove { parent, index }
+                | Patch::Move {
+                    parent,
+                    from: index,
+                    ..
+                } => {
+                    let mut path = parent.clone();
+                    path.push(*index);
+                    path
+                }
+                #[cfg(feature = "wasm")]
+                Patch::SetEvent { path, .. } | Patch::RemoveEvent { path, .. } => path.clone(),
+            })
+            .collect()
+    }
+
+    /// Applies top-level structural patches to a list of keys
+    fn apply_keys(keys: &[&str], patches: &[Patch]) -> Vec<String> {
+        let mut keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        for patch in patches {
+            match patch {
+                Patch::Insert { index, node, .. } => match node {
+                    Node::Element(el) => keys.insert(*index, el.key().to_string()),
+                    node => keys.insert(*index, node.to_string()),
+                },
+                Patch::Remove { index, .. } => {
+                    keys.remove(*index);
+                }
+                Patch::Move {
+                    from, count, to, ..
+                } => {
+                    let moved: Vec<_> = keys.drain(*from..*from + *count).collect();
+                    keys.splice(*to..*to, moved);
+                }
+                _ => {}
+            }
+        }
+        keys
+    }
+
+    fn keyed(keys: &[&str]) -> Node {
+        Node::Fragment(
+            keys.iter()
+                .map(|key| rsx!(<li key={*key}>{key}</li>))
+                .collect(),
+        )
+    }
+
+    fn group(scope: u32, key: &str) -> Node {
+        Node::Component(crate::nodes::ComponentNode {
+            scope: crate::signals::ScopeId::new(scope),
+            key: key.to_string(),
+            node: Box::new(rsx!(<><dt>{key}</dt><dd>{key}</dd></>)),
+        })
+    }
+
+    #[test]
+    fn test_identical_trees_produce_no_patches() {
+        let old = rsx!(<div class="a"><p>Hello</p><span>World</span></div>);
+        let new = rsx!(<div class="a"><p>Hello</p><span>World</span></div>);
+        assert!(Node::diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_text_is_updated_in_place() {
+        let (a, b) = ("one", "two");
+        let old = rsx!(<div><p>{a}</p></div>);
+        let new = rsx!(<div><p>{b}</p></div>);
+        let patches = Node::diff(&old, &new);
+        assert_eq!(paths(&patches), vec![vec![0, 0, 0]]);
+        assert!(matches!(&patches[0], Patch::SetText { text, .. } if text == "two"));
+    }
+
+    #[test]
+    fn test_attributes_are_set_and_removed() {
+        let old = rsx!(<div class="a" id="x" />);
+        let new = rsx!(<div class="b" title="t" />);
+        let patches = Node::diff(&old, &new);
+        let mut set = Vec::new();
+        let mut removed = Vec::new();
+        for patch in &patches {
+            match patch {
+                Patch::SetAttribute { name, value, .. } => set.push((name.clone(), value.clone())),
+                Patch::RemoveAttribute { name, .. } => removed.push(name.clone()),
+                _ => panic!("unexpected patch"),
+            }
+        }
+        assert_eq!(
+            set,
+            vec![
+                ("class".to_string(), "b".to_string()),
+                ("title".to_string(), "t".to_string())
+            ]
+        );
+        assert_eq!(removed, vec!["id".to_string()]);
+    }
+
+    #[test]
+    fn test_children_are_appended_and_removed() {
+        let old = rsx!(<ul><li>A</li></ul>);
+        let new = rsx!(<ul><li>A</li><li>B</li><li>C</li></ul>);
+        let patches = Node::diff(&old, &new);
+        assert_eq!(paths(&patches), vec![vec![0, 1], vec![0, 2]]);
+        assert!(patches.iter().all(|p| matches!(p, Patch::Insert { .. })));
+
+        let patches = Node::diff(&new, &old);
+        assert_eq!(paths(&patches), vec![vec![0, 2], vec![0, 1]]);
+        assert!(patches.iter().all(|p| matches!(p, Patch::Remove { .. })));
+    }
+
+    #[test]
+    fn test_changed_tag_replaces_node() {
+        let old = rsx!(<div><p>Hello</p></div>);
+        let new = rsx!(<div><span>Hello</span></div>);
+        let patches = Node::diff(&old, &new);
+        assert_eq!(paths(&patches), vec![vec![0, 0]]);
+        assert!(
+            matches!(&patches[0], Patch::Replace { node, .. } if node.to_string() == "<span>Hello</span>")
+        );
+    }
+
+    #[test]
+    fn test_fragments_are_flattened() {
+        let show = false;
+        let old = rsx!(<div><>First</><p>Second</p></div>);
+        let new = rsx!(<div>{when!(show => <b>Hidden</b>)}<>First</><p>Changed</p></div>);
+        let patches = Node::diff(&old, &new);
+        // the empty conditional takes no slot, so <p> keeps index 1
+        assert_eq!(paths(&patches), vec![vec![0, 1, 0]]);
+    }
+
+    #[test]
+    fn test_keyed_reorder_moves_only_displaced_items() {
+        let patches = Node::diff(&keyed(&["a", "b", "c", "d"]), &keyed(&["d", "a", "b", "c"]));
+        assert_eq!(patches.len(), 1);
+        assert!(matches!(
+            &patches[0],
+            Patch::Move {
+                from: 3,
+                count: 1,
+                to: 0,
+                ..
+            }
+        ));
+        assert_eq!(
+            apply_keys(&["a", "b", "c", "d"], &patches),
+            ["d", "a", "b", "c"]
+        );
+
+        let patches = Node::diff(&keyed(&["a", "b", "c", "d"]), &keyed(&["d", "c", "b", "a"]));
+        assert_eq!(
+            apply_keys(&["a", "b", "c", "d"], &patches),
+            ["d", "c", "b", "a"]
+        );
+        assert_eq!(patches.len(), 3);
+    }
+
+    #[test]
+    fn test_keyed_insert_and_remove_keep_other_nodes() {
+        let patches = Node::diff(&keyed(&["a", "b", "c"]), &keyed(&["a", "x", "c", "y"]));
+        assert_eq!(apply_keys(&["a", "b", "c"], &patches), ["a", "x", "c", "y"]);
+        // "a" and "c" are neither moved nor re-rendered
+        assert!(patches.iter().all(|patch| !matches!(
+            patch,
+            Patch::Move { .. } | Patch::Replace { .. } | Patch::SetText { .. }
+        )));
+    }
+
+    #[test]
+    fn test_keyed_content_is_diffed_after_move() {
+        let old = keyed(&["a", "b"]);
+        let new = Node::Fragment(vec![
+            rsx!(<li key="b">Changed</li>),
+            rsx!(<li key="a">a</li>),
+        ]);
+        let patches = Node::diff(&old, &new);
+        assert!(matches!(&patches[0], Patch::Move { .. }));
+        assert_eq!(paths(&patches[1..]), vec![vec![0, 0]]);
+        assert!(matches!(&patches[1], Patch::SetText { text, .. } if text == "Changed"));
+    }
+
+    #[test]
+    fn test_keyed_components_move_as_a_group() {
+        let old = Node::Fragment(vec![group(1, "a"), group(2, "b")]);
+        let new = Node::Fragment(vec![group(2, "b"), group(1, "a")]);
+        let patches = Node::diff(&old, &new);
+        assert_eq!(patches.len(), 1);
+        assert!(matches!(
+            &patches[0],
+            Patch::Move {
+                from: 2,
+                count: 2,
+                to: 0,
+                ..
+            }
+        ));
+    }
+}
@@ -1,7 +1,27 @@
 use crate::{
-    nodes::{Component, Node},
-    signals::run_scope,
+    nodes::{Component, ComponentNode, DynamicAttribute, DynamicNode, Node},
+    signals::{SignalValue, SignalVec, VecDiff, create_local_signal, run_child_scope, run_scope},
 };
+use alloc::{
+    collections::BTreeSet,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    any::TypeId,
+    cell::Cell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "wasm")]
+use crate::{
+    diff::Patch,
+    nodes::{Element, EventCallback},
+    signals::{Runtime, ScopeId},
+};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{JsCast, JsValue};
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen::prelude::wasm_bindgen]
@@ -11,108 +31,102 @@ extern "C" {
 }
 
 #[cfg(feature = "wasm")]
-mod element_cache {
-    use alloc::{collections::BTreeMap, string::String};
+mod mounts {
+    use crate::nodes::Node;
+    use alloc::vec::Vec;
     use core::cell::UnsafeCell;
 
-    #[derive(Debug)]
+    /// A root component rendered into a DOM element
+    pub(crate) struct Mount {
+        pub(crate) element: web_sys::Element,
+        /// The tree currently reflected in the DOM
+        pub(crate) tree: Node,
+    }
+
     // UnsafeCell wrapper for WASM single-threaded environment
-    struct ElementCache {
-        inner: UnsafeCell<Option<BTreeMap<String, web_sys::Element>>>,
+    struct Mounts {
+        inner: UnsafeCell<Vec<Mount>>,
     }
 
-    unsafe impl Sync for ElementCache {}
+    unsafe impl Sync for Mounts {}
 
-    static ELEMENT_CACHE: ElementCache = ElementCache {
-        inner: UnsafeCell::new(None),
+    static MOUNTS: Mounts = Mounts {
+        inner: UnsafeCell::new(Vec::new()),
     };
 
     // Safe in WASM because it's single-threaded
-    pub(crate) fn with_cache<F, R>(f: F) -> R
+    pub(crate) fn with_mounts<F, R>(f: F) -> R
     where
-        F: FnOnce(&mut BTreeMap<String, web_sys::Element>) -> R,
+        F: FnOnce(&mut Vec<Mount>) -> R,
     {
-        unsafe {
-            let cache = &mut *ELEMENT_CACHE.inner.get();
-            if cache.is_none() {
-                *cache = Some(BTreeMap::new());
-            }
-            f(cache.as_mut().unwrap())
-        }
+        unsafe { f(&mut *MOUNTS.inner.get()) }
     }
 }
 
 #[cfg(feature = "wasm")]
-trait WasmRender {
-    fn render(&self, mount: &web_sys::Element) -> Option<web_sys::Element>;
+fn document() -> web_sys::Document {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .expect("should have a document on window")
 }
 
 #[cfg(feature = "wasm")]
-impl WasmRender for crate::nodes::Element {
-    fn render(&self, mount: &web_sys::Element) -> Option<web_sys::Element> {
-        let element = web_sys::window()
-            .map(|window| window.document().map(|doc| doc.create_element(self.tag())))
-            .flatten()
-            .and_then(|el| el.ok());
-
-        if let Some(element) = element {
-            let _ = mount.append_child(&element);
-            let dangerous_inner_html = self.html();
-            if !dangerous_inner_html.is_empty() {
-                element.set_inner_html(&dangerous_inner_html);
-            }
-            // add attributes
-            for (name, value) in self.attributes() {
-                let _ = element.set_attribute(name, value);
-            }
-            // render childnode
-            for child in self.children() {
-                child.render(&element);
-            }
-            // attach events
-            for (event_type, callback) in self.events() {
-                attach_event_handler(&element, event_type, callback.clone());
-            }
+trait WasmRender {
+    /// Creates the DOM nodes for this node, in document order
+    fn create(&self, document: &web_sys::Document, out: &mut Vec<web_sys::Node>);
+}
 
-            element_cache::with_cache(|cache| {
-                use alloc::string::ToString;
-                cache.insert(self.key().to_string(), element.clone());
-            });
-            return Some(element);
-        }
-        None
+#[cfg(feature = "wasm")]
+impl WasmRender for Element {
+    fn create(&self, document: &web_sys::Document, out: &mut Vec<web_sys::Node>) {
+        let Ok(element) = document.create_element(self.tag()) else {
+            return;
+        };
+        let dangerous_inner_html = self.html();
+        if !dangerous_inner_html.is_empty() {
+            element.set_inner_html(dangerous_inner_html);
+        }
+        // add attributes
+        for (name, value) in self.attributes() {
+            let _ = element.set_attribute(name, value);
+        }
+        // render childnodes
+        let mut children = Vec::new();
+        for child in self.children() {
+            child.create(document, &mut children);
+        }
+        for child in &children {
+            let _ = element.append_child(child);
+        }
+        // attach events
+        for (event_type, callback) in self.events() {
+            attach_event_handler(&element, event_type, callback.clone());
+        }
+        out.push(element.into());
     }
 }
 
 #[cfg(feature = "wasm")]
 impl WasmRender for Node {
-    fn render(&self, mount: &web_sys::Element) -> Option<web_sys::Element> {
+    fn create(&self, document: &web_sys::Document, out: &mut Vec<web_sys::Node>) {
         match self {
-            Node::Text(text) => {
-                let current_text = mount.text_content().unwrap_or_default();
-                mount.set_text_content(Some(&(current_text + &text)));
-                return None;
-            }
-            Node::Element(el) => {
-                return el.render(mount);
-            }
-            Node::Fragment(fragment) => {
-                for child in fragment {
-                    child.render(mount);
-                }
-                return None;
-            }
-            Node::Comment(_) => {
-                // TODO: implement comment rendering
-                return None;
-            }
-            _ => None,
+            Node::Element(el) => el.create(document, out),
+            Node::Text(text) => out.push(document.create_text_node(text).into()),
+            Node::Comment(comment) => out.push(document.create_comment(comment).into()),
+            Node::Fragment(nodes) => nodes.iter().for_each(|node| node.create(document, out)),
+            Node::Component(component) => component.node().create(document, out),
+            Node::Dynamic(dynamic) => dynamic.node().create(document, out),
+            Node::Empty => {}
         }
     }
 }
 
 /// Renders a component with the given props
 ///
+/// When called while a component renders, the new component is matched to the
+/// one rendered at the same position among its kind the last time, and keeps
+/// that one's scope and signals.
+///
 /// # Example
 ///
 /// ```rust ignore
@@ -135,11 +149,226 @@ impl WasmRender for Node {
 ///     println!("{}", node.to_string());
 /// }
 /// ```
-pub fn component<C: Component>(props: C::Props) -> Node
+pub fn component<C: Component + 'static>(props: C::Props) -> Node
+where
+    <C as Component>::Props: Send + Sync + 'static,
+{
+    render_component::<C>(None, props, |_, _| true, |_| {})
+}
+
+/// Renders a component with the given props under a key
+///
+/// A keyed component keeps its scope, and so its signals, when its parent
+/// re-renders it with the same key, and its DOM nodes are moved rather than
+/// re-created when its position among its siblings changes. `rsx!` uses this
+/// for components given a `key` prop, which is not passed on to the props.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// struct Row;
+/// struct RowProps {
+///     label: &'static str,
+/// }
+///
+/// impl Component for Row {
+///     type Props = RowProps;
+///     fn render(props: &Self::Props) -> Node {
+///         rsx!(<li>{props.label}</li>)
+///     }
+/// }
+///
+/// let labels = ["first", "second"];
+/// let list = rsx!(
+///     <ul>
+///         {labels.iter().map(|label| rsx!(<Row key={label.to_string()} label={*label} />))}
+///     </ul>
+/// );
+/// assert_eq!(list.to_string(), "<ul><li>first</li><li>second</li></ul>");
+/// ```
+pub fn keyed_component<C: Component + 'static>(key: String, props: C::Props) -> Node
 where
     <C as Component>::Props: Send + Sync + 'static,
 {
-    render_component::<C>(props, |_| {})
+    render_component::<C>(Some(key), props, |_, _| true, |_| {})
+}
+
+/// Renders a component from `rsx!`, which passes `changed` to tell its props
+/// apart from the ones it rendered with last time
+#[doc(hidden)]
+pub fn child_component<C: Component + 'static>(
+    key: Option<String>,
+    props: C::Props,
+    changed: impl FnOnce(&C::Props, &C::Props) -> bool,
+) -> Node
+where
+    <C as Component>::Props: Send + Sync + 'static,
+{
+    render_component::<C>(key, props, changed, |_| {})
+}
+
+/// Compares two sets of props, for `rsx!` to tell whether a component needs to
+/// render again.
+///
+/// Props that implement `PartialEq` are compared with it through
+/// [`ComparableProps`], any others count as changed through [`OpaqueProps`].
+#[doc(hidden)]
+pub struct PropsChange<'a, T>(pub &'a T, pub &'a T);
+
+#[doc(hidden)]
+pub trait ComparableProps {
+    fn changed(&self) -> bool;
+}
+
+impl<T: PartialEq> ComparableProps for PropsChange<'_, T> {
+    fn changed(&self) -> bool {
+        self.0 != self.1
+    }
+}
+
+#[doc(hidden)]
+pub trait OpaqueProps {
+    fn changed(&self) -> bool;
+}
+
+impl<T> OpaqueProps for &PropsChange<'_, T> {
+    fn changed(&self) -> bool {
+        true
+    }
+}
+
+/// Renders a reactive closure as a node that keeps itself up to date
+///
+/// The closure runs in a scope of its own, so only this node is re-rendered
+/// when the signals it reads change. Closures without arguments used as
+/// children in `rsx!` are rendered with this, see [`DynamicNode`].
+pub fn dynamic<N: Into<Node>>(render: impl Fn() -> N + Send + Sync + 'static) -> Node {
+    match run_scope(move || render().into(), sync_dom) {
+        Node::Component(ComponentNode { scope, node, .. }) => {
+            Node::Dynamic(DynamicNode { scope, node })
+        }
+        node => node,
+    }
+}
+
+/// Renders a row for each item of a [`SignalVec`], keeping the rows in step
+/// with the operations applied to it.
+///
+/// In `rsx!`, `<For each={items} key={|item| ...}>{|item| ...}</For>` renders
+/// each item with the closure it is given as a child. Rows are told apart by
+/// their `key`, so that inserting or removing items only inserts or removes
+/// their rows, moved items move their rows along with their state, and only
+/// the rows of updated items render again. Rows may capture state of the
+/// component rendering them, so they all render again when it does.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Names() -> Node {
+///     let names = create_signal_vec(vec!["Ada", "Grace"]);
+///     rsx!(
+///         <ul>
+///             <For each={names} key={|name| name.to_string()}>
+///                 {|name| rsx!(<li>{*name}</li>)}
+///             </For>
+///         </ul>
+///     )
+/// }
+///
+/// assert_eq!(rsx!(<Names />).to_string(), "<ul><li>Ada</li><li>Grace</li></ul>");
+/// ```
+pub struct For;
+
+/// Marks the child scopes rows of a [`For`] render in
+struct ForRow;
+
+impl For {
+    /// Renders `row` for each item of `each`, telling rows apart by `key`
+    pub fn each<T, K, N>(
+        each: SignalVec<T>,
+        key: impl Fn(&T) -> K + Send + Sync + 'static,
+        row: impl Fn(&T) -> N + Send + Sync + 'static,
+    ) -> Node
+    where
+        T: SignalValue + Clone,
+        K: ToString,
+        N: Into<Node>,
+    {
+        let (key, row) = (Arc::new(key), Arc::new(row));
+        // set until the closure first runs, which is when the parent rendered
+        let fresh = AtomicBool::new(true);
+        run_child_scope(
+            None,
+            TypeId::of::<For>(),
+            (),
+            |_, _| true,
+            move |_| {
+                // the vector and version of it the rows were last rendered from
+                let seen = create_local_signal(Cell::new(None));
+                let (items, version) = (each.get(), each.version());
+                // rows may capture state of the parent, so they all render again
+                // with it, and another vector isn't followed from the last one
+                let fresh = fresh.swap(false, Ordering::Relaxed);
+                let diffs = seen
+                    .with_untracked(|seen| seen.replace(Some((each, version))))
+                    .flatten()
+                    .filter(|(seen, _)| !fresh && *seen == each)
+                    .and_then(|(_, seen)| each.diffs_since(seen));
+                // rows without an update since are left as they are
+                let updated: Option<BTreeSet<String>> = diffs.map(|diffs| {
+                    diffs
+                        .iter()
+                        .filter_map(|diff| match diff {
+                            VecDiff::UpdateAt { value, .. } => Some(key(value).to_string()),
+                            _ => None,
+                        })
+                        .collect()
+                });
+
+                let rows = items.into_iter().map(|item| {
+                    let id = key(&item).to_string();
+                    let changed = updated.as_ref().is_none_or(|updated| updated.contains(&id));
+                    let row = row.clone();
+                    run_child_scope(
+                        Some(id),
+                        TypeId::of::<ForRow>(),
+                        (),
+                        move |_, _| changed,
+                        move |_| row(&item).into(),
+                        sync_dom,
+                    )
+                });
+                Node::Fragment(rows.collect())
+            },
+            sync_dom,
+        )
+    }
+}
+
+/// Evaluates the dynamic attributes of an element, each in a scope of its own
+#[doc(hidden)]
+pub fn bind_attributes(mut node: Node, attributes: &[(String, DynamicAttribute)]) -> Node {
+    let Node::Element(el) = &mut node else {
+        return node;
+    };
+    for (name, attribute) in attributes {
+        let (attribute, binding) = (attribute.clone(), name.clone());
+        let output = run_scope(
+            move || Node::Text(attribute.value()),
+            move |node| sync_attribute(&binding, node),
+        );
+        if let Node::Component(ComponentNode { scope, node, .. }) = output
+            && let Node::Text(value) = *node
+        {
+            el.bind(name.clone(), value, scope);
+        }
+    }
+    node
 }
 
 #[cfg(feature = "wasm")]
@@ -166,24 +395,50 @@ where
 ///     render_root::<App>("#app");
 /// }
 /// ```
-pub fn render_root<C: Component>(selectors: &'static str)
+pub fn render_root<C: Component + 'static>(selectors: &'static str)
 where
     <C as Component>::Props: Default,
     <C as Component>::Props: Send + Sync + 'static,
 {
-    render_component::<C>(Default::default(), move |node| {
-        let window = web_sys::window().expect("no global `window` exists");
-        let document = window.document().expect("should have a document on window");
-        let mount_point = document
-            .query_selector(&selectors)
-            .expect("couldn't find element")
-            .expect("couldn't find element");
-        // clear mount point
-        while let Some(child) = mount_point.first_child() {
-            mount_point.remove_child(&child).ok();
-        }
-        node.render(&mount_point);
-    });
+    render_component::<C>(
+        None,
+        Default::default(),
+        |_, _| true,
+        move |node| {
+            let Node::Component(root) = node else {
+                return;
+            };
+            // later renders of the root are patched like any other component
+            let mounted = mounts::with_mounts(|mounts| {
+                mounts.iter().any(|mount| {
+                matches!(&mount.tree, Node::Component(tree) if tree.scope() == root.scope())
+            })
+            });
+            if mounted {
+                return;
+            }
+
+            let mount_point = document()
+                .query_selector(selectors)
+                .expect("couldn't find element")
+                .expect("couldn't find element");
+            // clear mount point
+            while let Some(child) = mount_point.first_child() {
+                mount_point.remove_child(&child).ok();
+            }
+            let mut nodes = Vec::new();
+            node.create(&document(), &mut nodes);
+            for child in &nodes {
+                mount_point.append_child(child).ok();
+            }
+            mounts::with_mounts(|mounts| {
+                mounts.push(mounts::Mount {
+                    element: mount_point,
+                    tree: node.clone(),
+                })
+            });
+        },
+    );
 }
 
 #[cfg(feature = "wasm")]
@@ -210,7 +465,7 @@ where
 ///     mount_to_body::<App>();
 /// }
 /// ```
-pub fn mount_to_body<C: Component>()
+pub fn mount_to_body<C: Component + 'static>()
 where
     <C as Component>::Props: Default,
     <C as Component>::Props: Send + Sync + 'static,
@@ -219,46 +474,351 @@ where
 }
 
 #[cfg(feature = "wasm")]
-fn attach_event_handler(
-    element: &web_sys::Element,
-    event_type: &str,
-    mut callback: crate::nodes::EventCallback,
-) {
+fn listener_key(event_type: &str) -> JsValue {
+    JsValue::from_str(&alloc::format!("__momenta_on{}", event_type))
+}
+
+#[cfg(feature = "wasm")]
+fn attach_event_handler(element: &web_sys::Element, event_type: &str, mut callback: EventCallback) {
     use alloc::boxed::Box;
     use wasm_bindgen::prelude::*;
 
+    detach_event_handler(element, event_type);
+
+    // handlers fire outside of any render, so they enter the runtime they were attached in,
+    // and every signal they set renders once when they return
+    let runtime = Runtime::current();
+    let (name, scope) = (String::from(event_type), callback.scope());
     let closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
-        callback.call(event);
+        runtime.run(|| {
+            crate::signals::record_event(&name, scope);
+            crate::signals::batch(|| callback.call(event))
+        });
     }) as Box<dyn FnMut(web_sys::Event)>);
+    // the JS garbage collector owns the closure from here on
+    let listener = closure.into_js_value();
 
     element
-        .add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())
+        .add_event_listener_with_callback(event_type, listener.unchecked_ref())
         .expect("Failed to add event listener");
 
-    closure.forget(); // Keep the closure alive
+    // keep a handle on the element so the listener can be swapped when patched
+    let _ = js_sys::Reflect::set(element, &listener_key(event_type), &listener);
 }
 
-fn render_component<C: Component>(
+#[cfg(feature = "wasm")]
+fn detach_event_handler(element: &web_sys::Element, event_type: &str) {
+    if let Ok(listener) = js_sys::Reflect::get(element, &listener_key(event_type))
+        && let Some(listener) = listener.dyn_ref::<js_sys::Function>()
+    {
+        let _ = element.remove_event_listener_with_callback(event_type, listener);
+    }
+}
+
+/// Finds the first node matching `matches` within a mounted tree, along with the
+/// path to the element containing it and the index of its first DOM node within
+/// that element
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+fn locate<'a>(
+    node: &'a Node,
+    matches: &dyn Fn(&Node) -> bool,
+    path: &mut Vec<usize>,
+    index: &mut usize,
+) -> Option<&'a Node> {
+    if matches(node) {
+        return Some(node);
+    }
+    match node {
+        Node::Component(component) => locate(component.node(), matches, path, index),
+        Node::Dynamic(dynamic) => locate(dynamic.node(), matches, path, index),
+        Node::Fragment(nodes) => nodes
+            .iter()
+            .find_map(|node| locate(node, matches, path, index)),
+        Node::Element(el) => {
+            path.push(*index);
+            let mut child_index = 0;
+            let found = el
+                .children()
+                .iter()
+                .find_map(|child| locate(child, matches, path, &mut child_index));
+            match found {
+                // the match is within this element, at its offset in there
+                Some(_) => *index = child_index,
+                None => {
+                    path.pop();
+                    *index += 1;
+                }
+            }
+            found
+        }
+        Node::Empty => None,
+        Node::Text(_) | Node::Comment(_) => {
+            *index += 1;
+            None
+        }
+    }
+}
+
+/// The path to the first element matching `matches` within a mounted tree
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+fn element_path(tree: &Node, matches: &dyn Fn(&Node) -> bool) -> Option<Vec<usize>> {
+    let (mut path, mut index) = (Vec::new(), 0);
+    locate(tree, matches, &mut path, &mut index)?;
+    path.push(index);
+    Some(path)
+}
+
+/// Finds the first node matching `matches` within a mounted tree, mutably
+#[cfg(feature = "wasm")]
+fn find_mut<'a>(node: &'a mut Node, matches: &dyn Fn(&Node) -> bool) -> Option<&'a mut Node> {
+    if matches(node) {
+        return Some(node);
+    }
+    match node {
+        Node::Component(component) => find_mut(&mut component.node, matches),
+        Node::Dynamic(dynamic) => find_mut(&mut dynamic.node, matches),
+        Node::Fragment(nodes) => nodes.iter_mut().find_map(|node| find_mut(node, matches)),
+        Node::Element(el) => el
+            .children_mut()
+            .iter_mut()
+            .find_map(|node| find_mut(node, matches)),
+        _ => None,
+    }
+}
+
+/// The output of the component or dynamic node rendered by `scope`, if `node` is one
+#[cfg(feature = "wasm")]
+fn rendered_by(node: &Node, scope: ScopeId) -> Option<&Node> {
+    match node {
+        Node::Component(component) if component.scope() == scope => Some(component.node()),
+        Node::Dynamic(dynamic) if dynamic.scope() == scope => Some(dynamic.node()),
+        _ => None,
+    }
+}
+
+/// Brings the DOM of a re-rendered scope up to date with its new output
+#[cfg(feature = "wasm")]
+fn patch_scope(scope: ScopeId, output: &Node) {
+    let renders = |node: &Node| rendered_by(node, scope).is_some();
+    let update = mounts::with_mounts(|mounts| {
+        mounts.iter_mut().find_map(|mount| {
+            let (mut path, mut index) = (Vec::new(), 0);
+            let old = locate(&mount.tree, &renders, &mut path, &mut index)?;
+            let patches = Node::diff(rendered_by(old, scope)?, output);
+            match find_mut(&mut mount.tree, &renders)? {
+                Node::Component(component) => *component.node = output.clone(),
+                Node::Dynamic(dynamic) => *dynamic.node = output.clone(),
+                _ => {}
+            }
+            Some((mount.element.clone(), path, index, patches))
+        })
+    });
+
+    // patches are applied outside the registry, as DOM mutations may fire events
+    if let Some((element, path, offset, patches)) = update
+        && let Some((container, _)) = resolve_parent(&element.into(), 0, &path)
+    {
+        apply_patches(&container, offset, patches);
+    }
+}
+
+/// Brings a dynamic attribute in the DOM up to date with its new value
+#[cfg(feature = "wasm")]
+fn patch_attribute(scope: ScopeId, name: &str, value: &str) {
+    let binds =
+        |node: &Node| matches!(node, Node::Element(el) if el.bindings().get(name) == Some(&scope));
+    let update = mounts::with_mounts(|mounts| {
+        mounts.iter_mut().find_map(|mount| {
+            let path = element_path(&mount.tree, &binds)?;
+            let Node::Element(el) = find_mut(&mut mount.tree, &binds)? else {
+                return None;
+            };
+            let name = String::from(name);
+            let patch = if value.is_empty() {
+                el.attributes_mut().remove(&name);
+                Patch::RemoveAttribute { path, name }
+            } else {
+                el.attributes_mut().insert(name.clone(), value.into());
+                Patch::SetAttribute {
+                    path,
+                    name,
+                    value: value.into(),
+                }
+            };
+            Some((mount.element.clone(), patch))
+        })
+    });
+
+    if let Some((element, patch)) = update {
+        apply_patches(&element.into(), 0, alloc::vec![patch]);
+    }
+}
+
+/// Resolves a patch path to a DOM node, where the first index is relative to `offset`
+#[cfg(feature = "wasm")]
+fn resolve(container: &web_sys::Node, offset: usize, path: &[usize]) -> Option<web_sys::Node> {
+    let (first, rest) = path.split_first()?;
+    let mut node = container.child_nodes().item((offset + first) as u32)?;
+    for index in rest {
+        node = node.child_nodes().item(*index as u32)?;
+    }
+    Some(node)
+}
+
+/// Resolves the parent of a child list, returning it with the offset of the list in it
+#[cfg(feature = "wasm")]
+fn resolve_parent(
+    container: &web_sys::Node,
+    offset: usize,
+    path: &[usize],
+) -> Option<(web_sys::Node, usize)> {
+    if path.is_empty() {
+        return Some((container.clone(), offset));
+    }
+    resolve(container, offset, path).map(|node| (node, 0))
+}
+
+#[cfg(feature = "wasm")]
+fn resolve_element(
+    container: &web_sys::Node,
+    offset: usize,
+    path: &[usize],
+) -> Option<web_sys::Element> {
+    resolve(container, offset, path).and_then(|node| node.dyn_into().ok())
+}
+
+/// Applies patches to the DOM nodes starting at `offset` within `container`
+#[cfg(feature = "wasm")]
+fn apply_patches(container: &web_sys::Node, offset: usize, patches: Vec<Patch>) {
+    let document = document();
+    for patch in patches {
+        match patch {
+            Patch::Replace { path, node } => {
+                let Some(target) = resolve(container, offset, &path) else {
+                    continue;
+                };
+                if let Some(parent) = target.parent_node() {
+                    let mut nodes = Vec::new();
+                    node.create(&document, &mut nodes);
+                    for new in &nodes {
+                        let _ = parent.insert_before(new, Some(&target));
+                    }
+                    let _ = parent.remove_child(&target);
+                }
+            }
+            Patch::Insert {
+                parent,
+                index,
+                node,
+            } => {
+                let Some((parent, base)) = resolve_parent(container, offset, &parent) else {
+                    continue;
+                };
+                let reference = parent.child_nodes().item((base + index) as u32);
+                let mut nodes = Vec::new();
+                node.create(&document, &mut nodes);
+                for new in &nodes {
+                    let _ = parent.insert_before(new, reference.as_ref());
+                }
+            }
+            Patch::Remove { parent, index } => {
+                let Some((parent, base)) = resolve_parent(container, offset, &parent) else {
+                    continue;
+                };
+                if let Some(child) = parent.child_nodes().item((base + index) as u32) {
+                    let _ = parent.remove_child(&child);
+                }
+            }
+            Patch::Move {
+                parent,
+                from,
+                count,
+                to,
+            } => {
+                let Some((parent, base)) = resolve_parent(container, offset, &parent) else {
+                    continue;
+                };
+                let children = parent.child_nodes();
+                let nodes: Vec<_> = (base + from..base + from + count)
+                    .filter_map(|index| children.item(index as u32))
+                    .collect();
+                // `to` counts the children left once the moved ones are taken out
+                let to = if to < from { to } else { to + count };
+                let reference = children.item((base + to) as u32);
+                for node in &nodes {
+                    let _ = parent.insert_before(node, reference.as_ref());
+                }
+            }
+            Patch::SetText { path, text } => {
+                if let Some(target) = resolve(container, offset, &path) {
+                    target.set_text_content(Some(&text));
+                }
+            }
+            Patch::SetAttribute { path, name, value } => {
+                if let Some(element) = resolve_element(container, offset, &path) {
+                    let _ = element.set_attribute(&name, &value);
+                }
+            }
+            Patch::RemoveAttribute { path, name } => {
+                if let Some(element) = resolve_element(container, offset, &path) {
+                    let _ = element.remove_attribute(&name);
+                }
+            }
+            Patch::SetEvent {
+                path,
+                name,
+                callback,
+            } => {
+                if let Some(element) = resolve_element(container, offset, &path) {
+                    attach_event_handler(&element, &name, callback);
+                }
+            }
+            Patch::RemoveEvent { path, name } => {
+                if let Some(element) = resolve_element(container, offset, &path) {
+                    detach_event_handler(&element, &name);
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the DOM in sync with a component or dynamic node after it renders
+#[cfg_attr(not(feature = "wasm"), allow(unused_variables))]
+fn sync_dom(node: &Node) {
+    #[cfg(feature = "wasm")]
+    if let Node::Component(component) = node {
+        patch_scope(component.scope(), component.node());
+    }
+}
+
+/// Keeps the DOM in sync with a dynamic attribute after it is re-evaluated
+#[cfg_attr(not(feature = "wasm"), allow(unused_variables))]
+fn sync_attribute(name: &str, node: &Node) {
+    #[cfg(feature = "wasm")]
+    if let Node::Component(binding) = node
+        && let Node::Text(value) = binding.node()
+    {
+        patch_attribute(binding.scope(), name, value);
+    }
+}
+
+fn render_component<C: Component + 'static>(
+    key: Option<String>,
     props: C::Props,
+    changed: impl FnOnce(&C::Props, &C::Props) -> bool,
     callback: impl Fn(&Node) + Send + Sync + 'static,
 ) -> Node
 where
     <C as Component>::Props: Send + Sync + 'static,
 {
-    run_scope(
-        move || C::render(&props),
+    run_child_scope(
+        key,
+        TypeId::of::<C>(),
+        props,
+        changed,
+        C::render,
         move |node| {
-            #[cfg(feature = "wasm")]
-            if let Node::Element(el) = node {
-                if let Some(element) =
-                    element_cache::with_cache(|cache| cache.get(el.key()).cloned())
-                {
-                    if let Some(parent) = element.parent_element() {
-                        parent.remove_child(&element).ok();
-                        el.render(&parent);
-                    }
-                }
-            }
+            sync_dom(node);
             callback(node)
         },
     )
@@ -593,6 +1153,12 @@ macro_rules! derive_elements {
                     /// **MDN Reference**: [data-* attributes](https://developer.mozilla.org/en-US/docs/Web/HTML/Global_attributes/data-*)
                     pub data_: Vec<(String, String)>,
 
+                    /// (Internal) Attributes bound to reactive closures, by attribute name.
+                    ///
+                    /// Filled in by `rsx!` for attributes given a closure without arguments,
+                    /// such as `title={move || ...}`. See [`DynamicAttribute`]($crate::nodes::DynamicAttribute).
+                    pub dynamic_: Vec<(String, $crate::nodes::DynamicAttribute)>,
+
                     // ============================================================================
                     // ARIA ACCESSIBILITY ATTRIBUTES
                     // ============================================================================
@@ -1147,14 +1713,15 @@ macro_rules! derive_elements {
                     type Props = [<HTML $tag:camel Element Props>];
 
                     fn render(props: &Self::Props) -> $crate::nodes::Node {
-                        $crate::nodes::Element::parse_tag_with_attributes(
+                        let node = $crate::nodes::Element::parse_tag_with_attributes(
                             &props.key,
                             stringify!($tag),
                             props.to_attributes(),
                             props.get_events(),
                             &props._dangerously_set_inner_html,
                             props.children.clone(),
-                        )
+                        );
+                        $crate::dom::bind_attributes(node, &props.dynamic_)
                     }
                 }
             }
@@ -2706,3 +3273,55 @@ pub mod elements {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{nodes::rsx, signals::ScopeId};
+    use alloc::{boxed::Box, vec};
+
+    fn component(scope: u32, node: Node) -> Node {
+        Node::Component(ComponentNode {
+            scope: ScopeId::new(scope),
+            key: String::new(),
+            node: Box::new(node),
+        })
+    }
+
+    #[test]
+    fn test_components_after_siblings_are_located_at_their_offset() {
+        let tree = rsx!(
+            <main>
+                <h1>Title</h1>
+                <div>
+                    <p>First</p>
+                    {component(1, rsx!(<><span>Second</span><span>Third</span></>))}
+                </div>
+            </main>
+        );
+        let renders =
+            |node: &Node| matches!(node, Node::Component(c) if c.scope() == ScopeId::new(1));
+        let (mut path, mut index) = (Vec::new(), 0);
+        assert!(locate(&tree, &renders, &mut path, &mut index).is_some());
+        assert_eq!((path, index), (vec![0, 1], 1));
+    }
+
+    #[test]
+    fn test_bound_elements_after_siblings_are_located() {
+        let mut bound = rsx!(<b>Bound</b>);
+        if let Node::Element(el) = &mut bound {
+            el.bind("title".into(), "value".into(), ScopeId::new(2));
+        }
+        let tree = rsx!(
+            <div>
+                <p>First</p>
+                <section>
+                    <i>Second</i>
+                    {bound}
+                </section>
+            </div>
+        );
+        let binds = |node: &Node| matches!(node, Node::Element(el) if el.bindings().get("title") == Some(&ScopeId::new(2)));
+        assert_eq!(element_path(&tree, &binds), Some(vec![0, 1, 1]));
+    }
+}
@@ -166,7 +166,12 @@
 //!
 
 extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+// lets the macros' `::momenta` paths resolve inside this crate too
+extern crate self as momenta;
 
+pub mod diff;
 pub mod dom;
 pub mod nodes;
 pub mod signals;
@@ -174,9 +179,16 @@ pub mod signals;
 pub use paste::paste;
 
 pub mod prelude {
+    pub use crate::dom::For;
     #[cfg(feature = "wasm")]
     pub use crate::dom::render_root;
-    pub use crate::nodes::{Component, Node};
-    pub use crate::signals::{Signal, SignalValue, create_effect, create_signal};
-    pub use momenta_macros::{SignalValue, component, rsx, when};
+    pub use crate::nodes::{Component, Fragment, Node};
+    pub use crate::signals::{
+        HistoryOptions, Memo, ReadSignal, Root, Runtime, Signal, SignalMap, SignalValue, SignalVec,
+        Store, WriteSignal, batch, create_effect, create_history_signal, create_local_signal,
+        create_memo, create_root, create_signal, create_signal_map, create_signal_pair,
+        create_signal_vec, create_store, flush, on_cleanup, provide_context, try_create_signal,
+        untrack, use_context,
+    };
+    pub use momenta_macros::{SignalValue, Store, component, rsx, when};
 }
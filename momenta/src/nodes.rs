@@ -1,18 +1,17 @@
 // For no_std, we need to use alloc collections instead of std
-use crate::signals::{Signal, SignalValue};
+use crate::signals::{Memo, ReadSignal, ScopeId, Signal, SignalValue};
 use alloc::{
     borrow::Cow,
+    boxed::Box,
     collections::BTreeMap,
     string::{String, ToString},
+    sync::Arc,
     vec::Vec,
 };
 use core::{fmt::Display, iter::FromIterator};
 
 pub use momenta_macros::{component, rsx, when};
 
-#[cfg(feature = "wasm")]
-use alloc::{boxed::Box, sync::Arc};
-
 /// A trait for converting values into HTML attribute strings.
 ///
 /// This trait is automatically implemented for any type that implements `ToString`,
@@ -61,6 +60,45 @@ impl<T: ToString> OptionAttribute for Option<T> {
     }
 }
 
+/// An attribute value that is kept up to date with the signals it reads.
+///
+/// Passing a closure without arguments as an element attribute creates one of
+/// these. The closure runs in its own reactive scope, so when a signal it reads
+/// changes only the attribute is updated and the component doesn't re-render.
+/// An empty value removes the attribute.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Greeting() -> Node {
+///     let name = create_signal("World");
+///     rsx!(<p title={move || format!("Hello {}", name.get())}>Hover me</p>)
+/// }
+///
+/// let node = rsx!(<Greeting />);
+/// assert_eq!(node.to_string(), "<p title=\"Hello World\">Hover me</p>");
+/// ```
+#[derive(Clone)]
+pub struct DynamicAttribute(Arc<dyn Fn() -> String + Send + Sync>);
+
+impl DynamicAttribute {
+    pub fn new<F, T>(value: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Attribute,
+    {
+        Self(Arc::new(move || value().value()))
+    }
+
+    /// Computes the current value of the attribute
+    pub fn value(&self) -> String {
+        (self.0)()
+    }
+}
+
 #[derive(Clone)]
 /// Represents an HTML element with its tag name, attributes, and children.
 ///
@@ -85,6 +123,8 @@ pub struct Element {
     attributes: BTreeMap<String, String>,
     inner_html: String,
     children: Vec<Node>,
+    /// The scopes keeping dynamic attributes up to date, by attribute name
+    bindings: BTreeMap<String, ScopeId>,
     #[cfg(feature = "wasm")]
     events: BTreeMap<String, EventCallback>,
     #[cfg(not(feature = "wasm"))]
@@ -108,6 +148,7 @@ impl Element {
             attributes,
             events,
             children,
+            bindings: BTreeMap::new(),
             inner_html: inner_html.to_string(),
         })
     }
@@ -128,6 +169,31 @@ impl Element {
         &self.children
     }
 
+    #[cfg(feature = "wasm")]
+    pub(crate) fn children_mut(&mut self) -> &mut Vec<Node> {
+        &mut self.children
+    }
+
+    #[cfg(feature = "wasm")]
+    pub(crate) fn attributes_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.attributes
+    }
+
+    /// Sets an attribute whose value is kept up to date by the scope `scope`
+    pub(crate) fn bind(&mut self, name: String, value: String, scope: ScopeId) {
+        if value.is_empty() {
+            self.attributes.remove(&name);
+        } else {
+            self.attributes.insert(name.clone(), value);
+        }
+        self.bindings.insert(name, scope);
+    }
+
+    #[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+    pub(crate) fn bindings(&self) -> &BTreeMap<String, ScopeId> {
+        &self.bindings
+    }
+
     pub(crate) fn html(&self) -> &String {
         &self.inner_html
     }
@@ -180,9 +246,111 @@ pub trait Component {
     fn render(props: &Self::Props) -> Node;
 }
 
-#[derive(Default)]
+#[derive(Default, PartialEq)]
 pub struct DefaultProps;
 
+/// Renders its children without a wrapper element.
+///
+/// This behaves like `<>...</>`, except that it can be given a `key`, which is
+/// handy for keyed lists where each item renders more than one node.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// let rows = ["a", "b"].iter().map(|id| {
+///     rsx!(
+///         <Fragment key={id.to_string()}>
+///             <dt>{id}</dt>
+///             <dd>Details</dd>
+///         </Fragment>
+///     )
+/// });
+/// let list = rsx!(<dl>{rows}</dl>);
+/// assert_eq!(list.to_string(), "<dl><dt>a</dt><dd>Details</dd><dt>b</dt><dd>Details</dd></dl>");
+/// ```
+pub struct Fragment;
+
+#[derive(Default)]
+pub struct FragmentProps {
+    pub children: Vec<Node>,
+}
+
+impl Component for Fragment {
+    type Props = FragmentProps;
+
+    fn render(props: &Self::Props) -> Node {
+        Node::Fragment(props.children.clone())
+    }
+}
+
+#[derive(Clone)]
+/// The output of a component render, tagged with the reactive scope that produced it.
+///
+/// Re-rendering a component only ever touches the part of the tree wrapped by its
+/// `ComponentNode`, which lets the renderer find and patch it in place.
+pub struct ComponentNode {
+    pub(crate) scope: ScopeId,
+    pub(crate) key: String,
+    pub(crate) node: Box<Node>,
+}
+
+impl ComponentNode {
+    /// The id of the scope that rendered this component
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    /// The key the component was rendered with, empty if it has none
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The node returned by the component's last render
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+}
+
+#[derive(Clone)]
+/// The current output of a reactive closure rendered as a child node.
+///
+/// Closures without arguments can be used as children in `rsx!`. Each one runs
+/// in its own reactive scope, so when a signal it reads changes only its output
+/// is re-rendered, while the surrounding component keeps the DOM it already has.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Counter() -> Node {
+///     let count = create_signal(0);
+///     rsx!(<p>{move || count.get()}</p>)
+/// }
+///
+/// let node = rsx!(<Counter />);
+/// assert_eq!(node.to_string(), "<p>0</p>");
+/// ```
+pub struct DynamicNode {
+    pub(crate) scope: ScopeId,
+    pub(crate) node: Box<Node>,
+}
+
+impl DynamicNode {
+    /// The id of the scope that keeps this node up to date
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    /// The node returned by the closure when it last ran
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+}
+
 #[derive(Clone)]
 /// Represents a node in the RSX tree.
 ///
@@ -191,6 +359,8 @@ pub struct DefaultProps;
 /// - Text content
 /// - Fragments (groups of nodes)
 /// - Comments
+/// - Components (the rendered output of a reactive scope)
+/// - Dynamic nodes (the output of a reactive closure)
 ///
 /// # Example
 ///
@@ -209,6 +379,10 @@ pub enum Node {
     Fragment(Vec<Node>),
     /// An HTML comment
     Comment(String),
+    /// A rendered component, transparent to its parent like a fragment
+    Component(ComponentNode),
+    /// The output of a reactive closure, transparent to its parent like a fragment
+    Dynamic(DynamicNode),
     Empty,
 }
 
@@ -232,6 +406,30 @@ impl Node {
             _ => None,
         }
     }
+
+    /// Replaces the output of the scopes nested in this node with what `latest`
+    /// reports they rendered last, if anything
+    pub(crate) fn refresh(&mut self, latest: &dyn Fn(ScopeId) -> Option<Node>) {
+        match self {
+            Node::Component(ComponentNode { scope, node, .. })
+            | Node::Dynamic(DynamicNode { scope, node }) => match latest(*scope) {
+                Some(output) => **node = output,
+                None => node.refresh(latest),
+            },
+            Node::Element(el) => {
+                for (name, scope) in el.bindings.clone() {
+                    if let Some(Node::Text(value)) = latest(scope) {
+                        el.bind(name, value, scope);
+                    }
+                }
+                el.children
+                    .iter_mut()
+                    .for_each(|child| child.refresh(latest));
+            }
+            Node::Fragment(nodes) => nodes.iter_mut().for_each(|node| node.refresh(latest)),
+            _ => {}
+        }
+    }
 }
 
 impl From<String> for Node {
@@ -363,6 +561,34 @@ where
     }
 }
 
+impl<T: SignalValue + PartialEq + Clone + 'static> From<ReadSignal<T>> for Node
+where
+    T: Into<Node>,
+{
+    fn from(value: ReadSignal<T>) -> Self {
+        value.get().into()
+    }
+}
+
+impl<T: SignalValue + PartialEq + Clone + 'static> From<Memo<T>> for Node
+where
+    T: Into<Node>,
+{
+    fn from(value: Memo<T>) -> Self {
+        value.get().into()
+    }
+}
+
+impl<F, R> From<F> for Node
+where
+    F: Fn() -> R + Send + Sync + 'static,
+    R: Into<Node>,
+{
+    fn from(value: F) -> Self {
+        crate::dom::dynamic(value)
+    }
+}
+
 impl<I, F, R> From<core::iter::Map<I, F>> for Node
 where
     I: Iterator,
@@ -422,6 +648,14 @@ impl Display for Node {
                 write!(f, "<!--{}-->", comment)?;
                 Ok(())
             }
+            Node::Component(component) => {
+                write!(f, "{}", component.node)?;
+                Ok(())
+            }
+            Node::Dynamic(dynamic) => {
+                write!(f, "{}", dynamic.node)?;
+                Ok(())
+            }
             Node::Empty => {
                 write!(f, "")?;
                 Ok(())
@@ -457,13 +691,35 @@ fn sanitize_html(input: &str) -> String {
     result
 }
 
+/// A shared event handler, run by one event at a time
+type Handler<E> = spin::Mutex<Box<dyn FnMut(E) + Send + Sync>>;
+
+/// Runs `handler` with `event`, or queues the event if the handler is busy,
+/// such as when it dispatches an event to itself. Queued events run in order
+/// once the handler returns.
+#[cfg_attr(not(any(feature = "wasm", test)), allow(dead_code))]
+fn dispatch<E: 'static>(handler: &Arc<Handler<E>>, event: E) {
+    let id = Arc::as_ptr(handler) as *const () as usize;
+    let Some(mut callback) = handler.try_lock() else {
+        let handler = handler.clone();
+        crate::signals::defer_event(id, Box::new(move || dispatch(&handler, event)));
+        return;
+    };
+    callback(event);
+    drop(callback);
+    while let Some(deferred) = crate::signals::take_deferred_event(id) {
+        deferred();
+    }
+}
+
+/// An event handler, along with the scope that rendered it
 #[cfg(feature = "wasm")]
-pub struct EventCallback(Option<Arc<spin::Mutex<Box<dyn FnMut(web_sys::Event) + Send + Sync>>>>);
+pub struct EventCallback(Option<Arc<Handler<web_sys::Event>>>, Option<ScopeId>);
 
 #[cfg(feature = "wasm")]
 impl Default for EventCallback {
     fn default() -> Self {
-        Self(None)
+        Self(None, None)
     }
 }
 
@@ -473,25 +729,85 @@ impl EventCallback {
     where
         F: FnMut(web_sys::Event) + Send + Sync + 'static,
     {
-        Self(Some(Arc::new(spin::Mutex::new(Box::new(callback)))))
+        Self(
+            Some(Arc::new(spin::Mutex::new(Box::new(callback)))),
+            crate::signals::get_current_scope(),
+        )
+    }
+
+    /// Creates a handler that isn't `Send`, such as one holding an `Rc` or a
+    /// DOM node, within the current scope.
+    ///
+    /// The handler is kept by the runtime, like the value of a local signal,
+    /// and replaced every time the scope renders. Calling it from another
+    /// thread panics.
+    #[track_caller]
+    pub fn local<F>(callback: F) -> Self
+    where
+        F: FnMut(web_sys::Event) + 'static,
+    {
+        let handler = LocalHandler(alloc::rc::Rc::new(core::cell::RefCell::new(callback)));
+        let local = crate::signals::create_local_signal(handler.clone());
+        local.set(handler);
+        Self::new(move |event| {
+            let handler = local.with_untracked(|handler| handler.0.clone());
+            // events dispatched to a busy handler are queued around it, so it
+            // can only be busy here if an old callback still runs it
+            if let Some(handler) = handler
+                && let Ok(mut handler) = handler.try_borrow_mut()
+            {
+                handler(event);
+            }
+        })
     }
 
     pub fn has_callback(&self) -> bool {
         self.0.is_some()
     }
 
+    /// The scope that rendered the handler, if any
+    pub(crate) fn scope(&self) -> Option<ScopeId> {
+        self.1
+    }
+
+    /// Returns true if both callbacks point to the same handler
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Runs the handler with the given event.
+    ///
+    /// A handler that synchronously dispatches an event to itself is still busy
+    /// handling the outer one, so the nested event is queued and runs right
+    /// after the handler returns.
     pub fn call(&mut self, event: web_sys::Event) {
-        if let Some(cb) = &mut self.0 {
-            let mut cb = cb.lock();
-            cb(event);
+        if let Some(handler) = &self.0 {
+            dispatch(handler, event);
         }
     }
 }
 
+/// A handler kept in a local signal, which only changes when replaced by
+/// another one
+#[cfg(feature = "wasm")]
+#[derive(Clone)]
+struct LocalHandler(alloc::rc::Rc<core::cell::RefCell<dyn FnMut(web_sys::Event)>>);
+
+#[cfg(feature = "wasm")]
+impl PartialEq for LocalHandler {
+    fn eq(&self, other: &Self) -> bool {
+        alloc::rc::Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 #[cfg(feature = "wasm")]
 impl Clone for EventCallback {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self(self.0.clone(), self.1)
     }
 }
 
@@ -505,3 +821,33 @@ where
         Self::new(callback)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_events_dispatched_to_a_busy_handler_run_after_it() {
+        let log = Arc::new(spin::Mutex::new(Vec::new()));
+        let this = Arc::new(spin::Once::<Arc<Handler<u32>>>::new());
+        let handler: Arc<Handler<u32>> = Arc::new(spin::Mutex::new(Box::new({
+            let (log, this) = (log.clone(), this.clone());
+            move |event| {
+                log.lock().push(event);
+                if event == 1 {
+                    let this = this.get().unwrap();
+                    dispatch(this, 2);
+                    dispatch(this, 3);
+                    log.lock().push(10);
+                }
+            }
+        })));
+        this.call_once(|| handler.clone());
+
+        dispatch(&handler, 1);
+        assert_eq!(*log.lock(), vec![1, 10, 2, 3]);
+        dispatch(&handler, 4);
+        assert_eq!(*log.lock(), vec![1, 10, 2, 3, 4]);
+    }
+}
@@ -1,125 +1,557 @@
-use crate::nodes::Node;
+use crate::nodes::{ComponentNode, Node};
 use alloc::{
     boxed::Box,
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    rc::Rc,
     string::{String, ToString},
     sync::Arc,
     vec::Vec,
 };
 use core::{
-    any::Any,
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
     cmp::Ordering,
     marker::PhantomData,
     ops::{AddAssign, DivAssign, MulAssign, Not, SubAssign},
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering as AtomicOrdering},
+    task::{Context, Poll, Waker},
 };
-use spin::Mutex;
+use futures_core::Stream;
 
 //==============================================================================
-// GLOBAL STATE
+// ARENA
 //==============================================================================
 
-/// Current scope being executed
-static CURRENT_SCOPE: Mutex<Option<usize>> = Mutex::new(None);
-/// Scope currently being rendered (0 = none)
-static RENDERING_SCOPE: Mutex<usize> = Mutex::new(0);
-/// Next available scope ID
-static NEXT_SCOPE_ID: Mutex<usize> = Mutex::new(1);
+/// Generational index of a slot in an [`Arena`].
+///
+/// Freed slots are reused, but each reuse bumps the slot's generation, so a key
+/// to a freed slot never resolves to whatever took its place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Key {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Slot map storing values by generational [`Key`]
+struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    /// Indices of freed slots, up for reuse
+    free: Vec<u32>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+impl<T> Arena<T> {
+    fn insert(&mut self, value: T) -> Key {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            return Key {
+                index,
+                generation: slot.generation,
+            };
+        }
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(value),
+        });
+        Key {
+            index: (self.slots.len() - 1) as u32,
+            generation: 0,
+        }
+    }
 
-/// Signal counter per scope
-static SCOPE_SIGNAL_COUNTERS: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
-/// Effect counter per scope
-static SCOPE_EFFECT_COUNTERS: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+    fn get(&self, key: Key) -> Option<&T> {
+        self.slots
+            .get(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
 
-/// All signal values by (scope_id, signal_id)
-static SIGNALS: Mutex<BTreeMap<(usize, usize), StoredValue>> = Mutex::new(BTreeMap::new());
-/// Signals that changed during current scope execution
-static SCOPE_SIGNAL_CHANGES: Mutex<BTreeSet<(usize, usize)>> = Mutex::new(BTreeSet::new());
+    fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        self.slots
+            .get_mut(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
 
-/// Functions that can be re-executed per scope
-static SCOPE_FUNCTIONS: Mutex<BTreeMap<usize, Box<dyn FnMut() -> Node + Send>>> =
-    Mutex::new(BTreeMap::new());
-/// Callbacks to run after scope renders
-static SCOPE_CALLBACKS: Mutex<BTreeMap<usize, Arc<dyn Fn(&Node) + Send + Sync>>> =
-    Mutex::new(BTreeMap::new());
-/// Effects by (scope_id, effect_id)
-static SCOPE_EFFECTS: Mutex<BTreeMap<(usize, usize), Box<dyn Fn() + Send>>> =
-    Mutex::new(BTreeMap::new());
+    /// Frees the slot of `key`, after which the key no longer resolves
+    fn remove(&mut self, key: Key) -> Option<T> {
+        let slot = self
+            .slots
+            .get_mut(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)?;
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(key.index);
+        Some(value)
+    }
 
-/// Which signals each scope depends on
-static SCOPE_DEPENDENCIES: Mutex<BTreeMap<usize, BTreeSet<(usize, usize)>>> =
-    Mutex::new(BTreeMap::new());
-/// Which scopes depend on each signal
-static SIGNAL_DEPENDENCIES: Mutex<BTreeMap<(usize, usize), BTreeSet<usize>>> =
-    Mutex::new(BTreeMap::new());
-/// Scopes waiting to re-render
-static PENDING_SCOPE_RENDERS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+    /// Number of occupied slots
+    fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+}
 
 //==============================================================================
-// TRAITS
+// RUNTIME
 //==============================================================================
 
-/// Values that can be stored in signals
-pub trait SignalValue: Send {
-    fn as_any(&self) -> Option<&dyn Any>;
+/// The reactive state owned by a runtime.
+///
+/// Borrows of these cells are never held while user code runs (scope functions,
+/// callbacks, effects, or closures passed to [`Signal::with`]), so that code is
+/// free to read and write any signal, create effects or trigger renders.
+#[derive(Default)]
+struct RuntimeState {
+    /// Tells this runtime apart from the others
+    id: RuntimeId,
+    /// Current scope being executed
+    current_scope: Cell<Option<ScopeId>>,
+    /// Scope currently being rendered
+    rendering_scope: Cell<Option<ScopeId>>,
+
+    /// All live scopes
+    scopes: RefCell<Arena<ScopeState>>,
+    /// All live signals
+    signals: RefCell<Arena<SignalState>>,
+    /// Signals that changed during current scope execution
+    scope_signal_changes: RefCell<BTreeSet<Key>>,
+    /// Scopes waiting to re-render, by depth so parents render before their children
+    pending_scope_renders: RefCell<BTreeSet<(usize, ScopeId)>>,
+    /// Number of [`batch`] calls currently running
+    batch_depth: Cell<usize>,
+    /// Whether a flush is already scheduled to run in a microtask
+    flush_scheduled: Cell<bool>,
+    /// Whether reads currently skip subscribing, see [`untrack`]
+    untracked: Cell<bool>,
+    /// What happened since recording started, see [`Runtime::start_recording`]
+    recording: RefCell<Option<Vec<RecordedEntry>>>,
+    /// Events dispatched to busy handlers, by handler, see [`defer_event`]
+    deferred_events: RefCell<VecDeque<(usize, Box<dyn FnOnce()>)>>,
 }
 
-macro_rules! impl_signal_value {
-    ($($t:ty),*) => {
-        $(
-            impl SignalValue for $t {
-                fn as_any(&self) -> Option<&dyn Any> {
-                    Some(self)
-                }
+/// Identifies a runtime, so handles can't reach into the state of a runtime
+/// other than the one that created them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct RuntimeId(u32);
+
+impl Default for RuntimeId {
+    fn default() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        RuntimeId(NEXT.fetch_add(1, AtomicOrdering::Relaxed))
+    }
+}
+
+/// Identifies a reactive scope within its runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScopeId(Key);
+
+#[cfg(test)]
+impl ScopeId {
+    pub(crate) fn new(index: u32) -> Self {
+        ScopeId(Key {
+            index,
+            generation: 0,
+        })
+    }
+}
+
+type ScopeFn = Box<dyn FnMut() -> Node + Send>;
+type ScopeCallback = Rc<dyn Fn(&Node)>;
+
+/// Everything a runtime keeps for one scope
+#[derive(Default)]
+struct ScopeState {
+    /// Function re-executed to render the scope
+    function: Option<ScopeFn>,
+    /// Callback to run after the scope renders
+    callback: Option<ScopeCallback>,
+    /// Signals created by the scope, in creation order
+    signals: Vec<Key>,
+    /// Effects created by the scope that haven't run yet
+    new_effects: Vec<ScopeId>,
+    /// Signals created so far during the current render
+    signal_cursor: usize,
+    /// Signals the scope read, and so depends on
+    dependencies: BTreeSet<Key>,
+    /// Key of a keyed scope, empty otherwise
+    key: String,
+    /// Number of scopes above this one in the ownership tree
+    depth: usize,
+    /// The scope that owns this one, if any
+    parent: Option<ScopeId>,
+    /// Values provided to descendants during the last render, by type
+    contexts: BTreeMap<TypeId, Rc<dyn Any>>,
+    /// Child scopes created (or reused) during the last render, owned by the scope
+    children: Vec<ScopeId>,
+    /// Child scopes created by the scope, by what they're matched by
+    matched_children: MatchedChildren,
+    /// Props the scope last rendered with, when it renders a component
+    props: Option<Arc<dyn Any + Send + Sync>>,
+    /// Output of the last render
+    output: Option<Node>,
+    /// The signal holding the value of a memo computed by the scope
+    memo: Option<Key>,
+    /// Whether an input of the memo changed since it was last computed
+    stale: bool,
+    /// Hooks to run before the scope renders again or is disposed
+    cleanups: Vec<Box<dyn FnOnce()>>,
+    /// Signals, effects and memos created by the scope, in creation order
+    #[cfg(debug_assertions)]
+    hooks: Vec<Hook>,
+    /// Hooks created so far during the current render
+    #[cfg(debug_assertions)]
+    hook_cursor: usize,
+}
+
+/// Where and as what a scope created a signal, effect or memo, to tell when a
+/// render creates them in a different order than the last one did
+#[cfg(debug_assertions)]
+#[derive(Clone, Copy, PartialEq)]
+struct Hook {
+    kind: &'static str,
+    type_name: &'static str,
+    location: &'static core::panic::Location<'static>,
+}
+
+#[cfg(debug_assertions)]
+impl core::fmt::Display for Hook {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.type_name {
+            "" => write!(f, "{} created at {}", self.kind, self.location),
+            type_name => write!(
+                f,
+                "{} of `{type_name}` created at {}",
+                self.kind, self.location
+            ),
+        }
+    }
+}
+
+/// A signal's value along with the scopes reading it
+struct SignalState {
+    /// The value, which is only mutated in place while no reader holds a snapshot.
+    ///
+    /// Values of all types share one arena, so each access checks the type
+    /// once. That's a single `TypeId` comparison, which `benches/signals.rs`
+    /// measures against the rest of a read.
+    value: Rc<dyn Any>,
+    /// Scopes to re-render when the value changes
+    subscribers: BTreeSet<ScopeId>,
+    /// Called once the signal is disposed, such as to end its streams
+    on_dispose: Vec<Box<dyn FnOnce()>>,
+    /// The scope that created the signal, and frees it
+    owner: ScopeId,
+}
+
+/// Stands in for the value of a signal while it's being mutated
+struct Mutating;
+
+/// Why a value isn't of the type a signal handle expects
+fn mismatch(value: &dyn Any) -> SignalError {
+    match value.is::<Mutating>() {
+        true => SignalError::BeingMutated,
+        false => SignalError::TypeMismatch,
+    }
+}
+
+/// The value of a signal being mutated, which goes back to its slot once
+/// dropped
+struct MutatedSlot<'a, T: 'static> {
+    rt: &'a RuntimeState,
+    id: Key,
+    value: Option<Rc<T>>,
+}
+
+impl<T: 'static> Drop for MutatedSlot<'_, T> {
+    fn drop(&mut self) {
+        let Some(value) = self.value.take() else {
+            return;
+        };
+        let Ok(mut signals) = self.rt.signals.try_borrow_mut() else {
+            return;
+        };
+        // a signal disposed meanwhile drops the value, once the borrow is released
+        let _unused = match signals.get_mut(self.id) {
+            Some(signal) => {
+                signal.value = value;
+                None
             }
-        )*
-    };
+            None => Some(value),
+        };
+        drop(signals);
+    }
+}
+
+/// An independent reactive runtime.
+///
+/// A runtime owns the scopes, signals and effects created while it runs, and
+/// frees them all once the last handle to it is dropped. Separate runtimes
+/// don't share any state, so they can render concurrently, for example to
+/// serve several SSR requests at once or to isolate unit tests.
+///
+/// A runtime belongs to the thread that created it. Code running outside of
+/// [`Runtime::run`] uses a default runtime of its thread. The `std` feature,
+/// which is on by default, keeps the current and default runtimes per thread.
+/// Without it, momenta only builds for single-threaded `wasm32`, where there is
+/// one thread to keep them for.
+///
+/// As a runtime can't be sent to another thread, only the thread that created
+/// a signal ever runs its runtime, which is what lets signal handles be sent
+/// even when their values can't.
+///
+/// ```compile_fail
+/// use momenta::prelude::*;
+///
+/// let runtime = Runtime::new();
+/// std::thread::spawn(move || runtime.run(|| ()));
+/// ```
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn App() -> Node {
+///     let count = create_signal(1);
+///     rsx!(<p>{count}</p>)
+/// }
+///
+/// let html = Runtime::new().run(|| rsx!(<App />).to_string());
+/// assert_eq!(html, "<p>1</p>");
+/// ```
+#[derive(Clone, Default)]
+pub struct Runtime {
+    state: Rc<RuntimeState>,
 }
 
-impl_signal_value!(
-    String,
-    &'static str,
-    i8,
-    i16,
-    i32,
-    i64,
-    i128,
-    isize,
-    u8,
-    u16,
-    u32,
-    u64,
-    u128,
-    usize,
-    f32,
-    f64,
-    bool,
-    char,
-    ()
+#[cfg(feature = "std")]
+std::thread_local! {
+    static CURRENT_RUNTIME: RefCell<Option<Runtime>> = const { RefCell::new(None) };
+    /// Runtime used by code that isn't running inside [`Runtime::run`]
+    static DEFAULT_RUNTIME: Runtime = Runtime::new();
+}
+
+#[cfg(not(any(
+    feature = "std",
+    all(target_arch = "wasm32", not(target_feature = "atomics"))
+)))]
+compile_error!(
+    "momenta needs the `std` feature to keep a runtime per thread, except on single-threaded wasm32"
 );
 
-impl<T: SignalValue + 'static> SignalValue for alloc::vec::Vec<T> {
-    fn as_any(&self) -> Option<&dyn Any> {
-        Some(self)
+/// The current and default runtime when there is no `std` to keep them per thread
+#[cfg(not(feature = "std"))]
+struct Runtimes {
+    current: RefCell<Option<Runtime>>,
+    default: RefCell<Option<Runtime>>,
+}
+
+// Sound because this is only built for wasm32 without atomics, where there is
+// no other thread to share the statics with
+#[cfg(not(feature = "std"))]
+unsafe impl Sync for Runtimes {}
+
+#[cfg(not(feature = "std"))]
+static RUNTIMES: Runtimes = Runtimes {
+    current: RefCell::new(None),
+    default: RefCell::new(None),
+};
+
+impl Runtime {
+    /// Creates a new runtime with no scopes or signals
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` with this runtime as the current one and returns its result.
+    ///
+    /// Runs can be nested, the previous runtime becomes current again once `f`
+    /// returns.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct RuntimeGuard {
+            previous: Option<Runtime>,
+        }
+
+        impl Drop for RuntimeGuard {
+            fn drop(&mut self) {
+                Runtime::swap_current(self.previous.take());
+            }
+        }
+
+        let _guard = RuntimeGuard {
+            previous: Self::swap_current(Some(self.clone())),
+        };
+        f()
+    }
+
+    /// Returns the runtime code is currently running in
+    pub fn current() -> Self {
+        #[cfg(feature = "std")]
+        return CURRENT_RUNTIME
+            .with(|current| current.borrow().clone())
+            .unwrap_or_else(|| DEFAULT_RUNTIME.with(Runtime::clone));
+        #[cfg(not(feature = "std"))]
+        return RUNTIMES.current.borrow().clone().unwrap_or_else(|| {
+            RUNTIMES
+                .default
+                .borrow_mut()
+                .get_or_insert_with(Runtime::new)
+                .clone()
+        });
+    }
+
+    /// Number of scopes alive in this runtime.
+    ///
+    /// Meant for debugging and leak tests: rendering the same tree again
+    /// shouldn't change it.
+    pub fn live_scopes(&self) -> usize {
+        self.state.scopes.borrow().len()
+    }
+
+    fn swap_current(runtime: Option<Runtime>) -> Option<Runtime> {
+        #[cfg(feature = "std")]
+        return CURRENT_RUNTIME.with(|current| current.replace(runtime));
+        #[cfg(not(feature = "std"))]
+        return RUNTIMES.current.replace(runtime);
     }
 }
 
-impl<T: SignalValue + 'static> SignalValue for Option<T> {
-    fn as_any(&self) -> Option<&dyn Any> {
-        Some(self)
+/// The state of the current runtime
+fn runtime() -> Rc<RuntimeState> {
+    Runtime::current().state
+}
+
+/// What a child scope is matched by across renders of its parent
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum ChildKey {
+    /// The key it was rendered with
+    Key(String),
+    /// Its position among the unkeyed children of the same kind
+    Position(usize),
+}
+
+/// Child scopes by component type and key or position
+#[derive(Default)]
+struct MatchedChildren {
+    /// Children created during the previous render, up for reuse until the
+    /// current render ends
+    previous: BTreeMap<(TypeId, ChildKey), ScopeId>,
+    /// Children created (or reused) during the current render
+    current: BTreeMap<(TypeId, ChildKey), ScopeId>,
+    /// Unkeyed children of each kind created so far during the current render
+    positions: BTreeMap<TypeId, usize>,
+}
+
+impl MatchedChildren {
+    /// Starts matching the children of a new render against the last one
+    fn start(&mut self) {
+        self.previous = core::mem::take(&mut self.current);
+        self.positions.clear();
+    }
+
+    /// Works out what a new child is matched by, taking over the scope that
+    /// matched it in the previous render if any
+    fn take(&mut self, kind: TypeId, key: Option<String>) -> ((TypeId, ChildKey), Option<ScopeId>) {
+        let key = key.map(ChildKey::Key).unwrap_or_else(|| {
+            let position = self.positions.entry(kind).or_default();
+            *position += 1;
+            ChildKey::Position(*position - 1)
+        });
+        let reused = self.previous.remove(&(kind, key.clone()));
+        ((kind, key), reused)
     }
 }
 
+//==============================================================================
+// TRAITS
+//==============================================================================
+
+/// Values that can be stored in signals, which is any `Send` type that doesn't
+/// borrow anything.
+///
+/// The trait is implemented for all of them, so it can't be implemented by
+/// hand: `impl SignalValue for MyType` blocks conflict with it and have to be
+/// deleted, and `#[derive(SignalValue)]` is a no-op kept so that code using it
+/// still compiles.
+pub trait SignalValue: Any + Send {}
+
+impl<T: Any + Send> SignalValue for T {}
+
 //==============================================================================
 // SIGNAL TYPE
 //==============================================================================
 
-/// Reactive value that triggers re-renders when changed
-#[derive(Copy, Debug)]
+/// Reactive value that triggers re-renders when changed.
+///
+/// The value is kept by the runtime the signal was created in, so a handle can
+/// be sent anywhere, even when the value isn't `Send`, but only used on the
+/// thread of that runtime.
+#[derive(Debug)]
 pub struct Signal<T> {
-    id: (usize, usize),
-    _marker: PhantomData<T>,
+    id: Key,
+    runtime: RuntimeId,
+    /// Where the signal was created, to point at it when it's misused
+    #[cfg(debug_assertions)]
+    created_at: &'static core::panic::Location<'static>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Why a signal couldn't be accessed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalError {
+    /// The scope that created the signal was disposed, freeing it
+    Disposed,
+    /// The signal holds a value of another type
+    TypeMismatch,
+    /// The value is being mutated by `update` or the like, so it can't be
+    /// accessed until that returns
+    BeingMutated,
+    /// The runtime that created the signal isn't the current one
+    MissingRuntime,
+}
+
+impl core::fmt::Display for SignalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SignalError::Disposed => {
+                write!(
+                    f,
+                    "signal was used after the scope that created it was disposed"
+                )
+            }
+            SignalError::TypeMismatch => write!(f, "signal holds a value of another type"),
+            SignalError::BeingMutated => write!(
+                f,
+                "signal was accessed while its value was being mutated, such as from within \
+                 its own `update`"
+            ),
+            SignalError::MissingRuntime => write!(
+                f,
+                "signal was used outside of the runtime that created it, which may belong \
+                 to another thread"
+            ),
+        }
+    }
 }
 
+impl core::error::Error for SignalError {}
+
 impl<T: SignalValue + Not<Output = bool> + Clone + 'static> Not for Signal<T> {
     type Output = bool;
     fn not(self) -> Self::Output {
@@ -140,18 +572,77 @@ impl<T: SignalValue + Clone + 'static> Signal<T> {
 
 impl<T> Clone for Signal<T> {
     fn clone(&self) -> Self {
+        *self
+    }
+}
+
+// a handle is copied into closures whatever the value, which stays in place
+impl<T> Copy for Signal<T> {}
+
+impl<T> Signal<T> {
+    /// Handle to a signal of the current runtime
+    #[track_caller]
+    fn from_key(id: Key) -> Self {
         Signal {
-            id: self.id,
+            id,
+            runtime: runtime().id,
+            #[cfg(debug_assertions)]
+            created_at: core::panic::Location::caller(),
             _marker: PhantomData,
         }
     }
+
+    /// The state of the runtime that created the signal, which has to be the
+    /// current one
+    fn runtime(&self) -> Result<Rc<RuntimeState>, SignalError> {
+        // runtimes never leave their thread, so finding the signal's runtime
+        // also means running on the thread that owns its value
+        let rt = runtime();
+        if rt.id != self.runtime {
+            return Err(SignalError::MissingRuntime);
+        }
+        Ok(rt)
+    }
+
+    /// Calls `f` once the signal is disposed, or right away if it already is
+    fn on_dispose(&self, f: impl FnOnce() + 'static) {
+        let Ok(rt) = self.runtime() else {
+            return f();
+        };
+        let mut signals = rt.signals.borrow_mut();
+        match signals.get_mut(self.id) {
+            Some(signal) => signal.on_dispose.push(Box::new(f)),
+            None => {
+                drop(signals);
+                f()
+            }
+        }
+    }
+
+    /// Access the value of a signal whose type is known, panicking if it
+    /// can't be
+    fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R
+    where
+        T: 'static,
+    {
+        self.try_with(f).unwrap_or_else(|error| self.fail(error))
+    }
+
+    /// Panics with `error`, pointing at where the signal was created in debug
+    /// builds
+    fn fail(&self, error: SignalError) -> ! {
+        #[cfg(debug_assertions)]
+        panic!("{error} (signal created at {})", self.created_at);
+        #[cfg(not(debug_assertions))]
+        panic!("{error} (signal {:?})", self.id);
+    }
 }
 
 impl<T: SignalValue + PartialEq + Clone + core::ops::Add<Output = T> + 'static> AddAssign<T>
     for Signal<T>
 {
     fn add_assign(&mut self, rhs: T) {
-        self.set(self.get() + rhs);
+        self.replace_if_changed(|value| value + rhs);
     }
 }
 
@@ -159,7 +650,7 @@ impl<T: SignalValue + PartialEq + Clone + core::ops::Sub<Output = T> + 'static>
     for Signal<T>
 {
     fn sub_assign(&mut self, rhs: T) {
-        self.set(self.get() - rhs);
+        self.replace_if_changed(|value| value - rhs);
     }
 }
 
@@ -167,7 +658,7 @@ impl<T: SignalValue + PartialEq + Clone + core::ops::Mul<Output = T> + 'static>
     for Signal<T>
 {
     fn mul_assign(&mut self, rhs: T) {
-        self.set(self.get() * rhs);
+        self.replace_if_changed(|value| value * rhs);
     }
 }
 
@@ -175,7 +666,7 @@ impl<T: SignalValue + PartialEq + Clone + core::ops::Div<Output = T> + 'static>
     for Signal<T>
 {
     fn div_assign(&mut self, rhs: T) {
-        self.set(self.get() / rhs);
+        self.replace_if_changed(|value| value / rhs);
     }
 }
 
@@ -215,7 +706,7 @@ impl<T: SignalValue + PartialEq + Clone + 'static> PartialEq<Signal<T>> for Sign
 impl Signal<bool> {
     /// Toggle the boolean value
     pub fn toggle(&self) {
-        self.set(!self.get());
+        self.update(|value| *value = !*value);
     }
 
     /// Set to true
@@ -233,16 +724,16 @@ impl Signal<bool> {
 impl<T: SignalValue + PartialEq + Clone + 'static> Signal<Vec<T>> {
     /// Push an item to the vector
     pub fn push(&self, item: T) {
-        let mut vec = self.get();
-        vec.push(item);
-        self.set(vec);
+        self.update(|vec| vec.push(item));
     }
 
     /// Pop an item from the vector
     pub fn pop(&self) -> Option<T> {
-        let mut vec = self.get();
-        let result = vec.pop();
-        self.set(vec);
+        let mut result = None;
+        self.update_if(|vec| {
+            result = vec.pop();
+            result.is_some()
+        });
         result
     }
 
@@ -258,7 +749,38 @@ impl<T: SignalValue + PartialEq + Clone + 'static> Signal<Vec<T>> {
 
     /// Clear the vector
     pub fn clear(&self) {
-        self.set(Vec::new());
+        self.update_if(|vec| {
+            let changed = !vec.is_empty();
+            vec.clear();
+            changed
+        });
+    }
+
+    /// A lens on the item at `index`, or `None` past the end. Readers of the
+    /// lens only depend on that item, so they don't render again when other
+    /// items change.
+    ///
+    /// Like memos, the lens is created once per position in the current scope.
+    #[track_caller]
+    pub fn at(&self, index: usize) -> Memo<Option<T>> {
+        let vec = *self;
+        create_memo(move || vec.with(|vec| vec.get(index).cloned()).flatten())
+    }
+
+    /// Replace the item at `index`, only notifying readers if it changed.
+    /// Returns whether there was an item to replace.
+    pub fn set_at(&self, index: usize, item: T) -> bool {
+        let mut found = false;
+        self.update_if(|vec| {
+            let Some(current) = vec.get_mut(index) else {
+                return false;
+            };
+            found = true;
+            let changed = *current != item;
+            *current = item;
+            changed
+        });
+        found
     }
 }
 
@@ -286,36 +808,42 @@ impl<T: SignalValue + Clone + 'static> Signal<Vec<T>> {
     }
 }
 
-impl<T: SignalValue + 'static> Signal<T> {
-    /// Access signal value immutably
+impl<T: 'static> Signal<T> {
+    /// Access signal value immutably, or `None` if it holds another type
     pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
-        if let Some(current_scope) = get_current_scope() {
-            {
-                let mut signal_deps = SIGNAL_DEPENDENCIES.lock();
-                signal_deps
-                    .entry(self.id)
-                    .or_insert_with(BTreeSet::new)
-                    .insert(current_scope);
+        match self.try_with(f) {
+            Ok(result) => Some(result),
+            Err(SignalError::TypeMismatch) => None,
+            Err(error) => self.fail(error),
+        }
+    }
+
+    /// Access signal value immutably, or tell why it can't be
+    pub fn try_with<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, SignalError> {
+        let rt = self.runtime()?;
+        let current_scope = get_current_scope().filter(|_| !rt.untracked.get());
+        let value = {
+            let mut signals = rt.signals.borrow_mut();
+            let signal = signals.get_mut(self.id).ok_or(SignalError::Disposed)?;
+            let value = signal
+                .value
+                .clone()
+                .downcast::<T>()
+                .map_err(|value| mismatch(&*value))?;
+            if let Some(scope_id) = current_scope {
+                signal.subscribers.insert(scope_id);
             }
-            {
-                let mut scope_deps = SCOPE_DEPENDENCIES.lock();
-                scope_deps
-                    .entry(current_scope)
-                    .or_insert_with(BTreeSet::new)
-                    .insert(self.id);
-            }
-        }
-
-        let signals = SIGNALS.lock();
-        signals
-            .get(&self.id)
-            .and_then(|stored| {
-                stored
-                    .value
-                    .as_any()
-                    .and_then(|any| any.downcast_ref::<T>())
-            })
-            .map(|val| f(val))
+            value
+        };
+        if let Some(scope_id) = current_scope
+            && let Some(scope) = rt.scopes.borrow_mut().get_mut(scope_id.0)
+        {
+            scope.dependencies.insert(self.id);
+        }
+
+        // `f` gets a snapshot of the value, so no borrow of the runtime is held
+        // while it runs and it may read or write any signal, this one included
+        Ok(f(&value))
     }
 
     /// Update signal value and trigger re-renders if changed
@@ -323,35 +851,153 @@ impl<T: SignalValue + 'static> Signal<T> {
     where
         T: PartialEq,
     {
-        let mut changed = false;
-
-        {
-            let mut signals = SIGNALS.lock();
-            if let Some(stored) = signals.get_mut(&self.id) {
-                if let Some(current_val) = stored
-                    .value
-                    .as_any()
-                    .and_then(|any| any.downcast_ref::<T>())
-                {
-                    if current_val != &value {
-                        *stored = StoredValue {
-                            value: Box::new(value),
-                        };
-                        changed = true;
-                    }
-                }
-            }
+        if let Err(error) = self.try_set(value) {
+            self.fail(error)
         }
+    }
+
+    /// Update signal value and trigger re-renders if changed, or tell why it
+    /// can't be
+    pub fn try_set(&self, value: T) -> Result<(), SignalError>
+    where
+        T: PartialEq,
+    {
+        let rt = self.runtime()?;
+        let current = rt
+            .signals
+            .borrow()
+            .get(self.id)
+            .map(|signal| signal.value.clone())
+            .ok_or(SignalError::Disposed)?;
+        // compared outside of the borrow, as `PartialEq` is user code too
+        let changed = match current.downcast_ref::<T>() {
+            Some(current) => current != &value,
+            None => return Err(mismatch(&*current)),
+        };
 
         if changed {
-            {
-                let mut changes = SCOPE_SIGNAL_CHANGES.lock();
-                changes.insert(self.id);
-            }
+            let value = Rc::new(value);
+            let subscribers = {
+                let mut signals = rt.signals.borrow_mut();
+                let signal = signals.get_mut(self.id).ok_or(SignalError::Disposed)?;
+                signal.value = value.clone();
+                signal.subscribers.clone()
+            };
+            record_set(&rt, self.id, &*value);
+            drop(value);
+            self.notify(&rt, subscribers);
+        }
+        // `current` may be the last reference to the old value, whose drop
+        // is user code as well
+        drop(current);
+        Ok(())
+    }
+
+    /// Mutate signal value in place and trigger re-renders, without comparing
+    /// it.
+    ///
+    /// The value is only cloned if a reader still holds it, such as when
+    /// updating a signal from within its own `with`. `f` must not read this
+    /// signal.
+    pub fn update(&self, f: impl FnOnce(&mut T))
+    where
+        T: Clone,
+    {
+        self.with_mut(f);
+    }
+
+    /// Mutate signal value in place and trigger re-renders, returning what
+    /// `f` returns
+    pub fn with_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> R
+    where
+        T: Clone,
+    {
+        self.mutate(|value| (f(value), true))
+    }
+
+    /// Mutate signal value in place, triggering re-renders only if `f` returns
+    /// `true`, which is returned as well
+    pub fn update_if(&self, f: impl FnOnce(&mut T) -> bool) -> bool
+    where
+        T: Clone,
+    {
+        self.mutate(|value| {
+            let changed = f(value);
+            (changed, changed)
+        })
+    }
+
+    /// Replaces the value with what `f` computes from it, triggering
+    /// re-renders only if that is a different value
+    fn replace_if_changed(&self, f: impl FnOnce(T) -> T)
+    where
+        T: PartialEq + Clone,
+    {
+        self.update_if(|value| {
+            let next = f(value.clone());
+            let changed = *value != next;
+            *value = next;
+            changed
+        });
+    }
 
-            if get_current_scope().is_none() {
-                render_scope(self.id.0);
+    fn mutate<R>(&self, f: impl FnOnce(&mut T) -> (R, bool)) -> R
+    where
+        T: Clone,
+    {
+        let rt = self.runtime().unwrap_or_else(|error| self.fail(error));
+        // the value leaves its slot while `f` runs, so no borrow is held
+        let value = {
+            let mut signals = rt.signals.borrow_mut();
+            let signal = signals
+                .get_mut(self.id)
+                .unwrap_or_else(|| self.fail(SignalError::Disposed));
+            if !signal.value.is::<T>() {
+                self.fail(mismatch(&*signal.value))
             }
+            core::mem::replace(&mut signal.value, Rc::new(Mutating))
+        };
+        let value = value
+            .downcast::<T>()
+            .unwrap_or_else(|_| self.fail(SignalError::TypeMismatch));
+        // puts the value back once done, even if `f` panics
+        let mut slot = MutatedSlot {
+            rt: &rt,
+            id: self.id,
+            value: Some(value),
+        };
+        let Some(value) = slot.value.as_mut() else {
+            unreachable!()
+        };
+        // readers still holding the value keep it, while the signal gets a
+        // copy of its own to mutate
+        let (result, changed) = f(Rc::make_mut(value));
+
+        let recorded = changed.then(|| slot.value.clone()).flatten();
+        drop(slot);
+        if let Some(value) = recorded {
+            let subscribers = rt
+                .signals
+                .borrow()
+                .get(self.id)
+                .map(|signal| signal.subscribers.clone())
+                .unwrap_or_else(|| self.fail(SignalError::Disposed));
+            record_set(&rt, self.id, &*value);
+            drop(value);
+            self.notify(&rt, subscribers);
+        }
+        result
+    }
+
+    /// Queues renders for the scopes subscribed to this signal
+    fn notify(&self, rt: &RuntimeState, subscribers: BTreeSet<ScopeId>) {
+        if rt.rendering_scope.get().is_some() {
+            rt.scope_signal_changes.borrow_mut().insert(self.id);
+        } else {
+            // only the scopes that read the signal need to render again, which
+            // may be a dynamic node rather than the component that owns it
+            subscribers.into_iter().for_each(schedule);
+            request_flush();
         }
     }
 
@@ -360,443 +1006,5162 @@ impl<T: SignalValue + 'static> Signal<T> {
     where
         T: Clone,
     {
-        self.with(|val| val.clone()).unwrap()
+        self.try_get().unwrap_or_else(|error| self.fail(error))
+    }
+
+    /// Get cloned value, or tell why it can't be
+    pub fn try_get(&self) -> Result<T, SignalError>
+    where
+        T: Clone,
+    {
+        self.try_with(|val| val.clone())
+    }
+
+    /// Access signal value immutably without the current scope depending on it
+    pub fn with_untracked<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        untrack(|| self.with(f))
+    }
+
+    /// Get cloned value without the current scope depending on it
+    pub fn get_untracked(&self) -> T
+    where
+        T: Clone,
+    {
+        untrack(|| self.get())
+    }
+
+    /// Same as [`Signal::get_untracked`]
+    pub fn peek(&self) -> T
+    where
+        T: Clone,
+    {
+        self.get_untracked()
     }
 }
 
-struct StoredValue {
-    value: Box<dyn SignalValue>,
+/// Runs `f` without the current scope depending on the signals and memos it
+/// reads, so changes to them don't render it again.
+///
+/// Scopes, effects and memos created or rendered within `f` still track their
+/// own reads.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Greeting() -> Node {
+///     let name = create_signal("World");
+///     let greeting = create_signal("Hello");
+///     // renders again when `name` changes, but not for a new greeting
+///     let text = format!("{}, {}!", untrack(|| greeting.get()), name.get());
+///     rsx!(<p>{text}</p>)
+/// }
+/// ```
+pub fn untrack<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = UntrackGuard {
+        previous: runtime().untracked.replace(true),
+    };
+    f()
 }
 
 //==============================================================================
-// SIGNAL CREATION
+// READ AND WRITE HALVES
 //==============================================================================
 
-/// Signal initialization options
-pub enum SignalInit<T> {
-    Value(T),
-    InitFn(Box<dyn Fn() -> T + Send + 'static>),
-}
-
-impl<T: SignalValue> From<T> for SignalInit<T> {
-    fn from(value: T) -> Self {
-        SignalInit::Value(value)
-    }
+/// The read half of a signal, see [`create_signal_pair`].
+///
+/// Reading it subscribes the current scope like reading the signal itself, but
+/// there is no way to change the value through it.
+#[derive(Debug)]
+pub struct ReadSignal<T> {
+    signal: Signal<T>,
 }
 
+/// The write half of a signal, see [`create_signal_pair`].
+///
+/// Writing through it triggers re-renders like writing the signal itself, but
+/// there is no way to read the value through it.
 #[derive(Debug)]
-pub enum SignalCreationError {
-    OutsideScope,
+pub struct WriteSignal<T> {
+    signal: Signal<T>,
 }
 
-impl core::fmt::Display for SignalCreationError {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "Signals can only be created within a scope context")
+impl<T> Clone for ReadSignal<T> {
+    fn clone(&self) -> Self {
+        *self
     }
 }
 
-/// Create new signal within current scope
-pub fn create_signal<T, I>(init: I) -> Signal<T>
-where
-    T: SignalValue + PartialEq + 'static,
-    I: Into<SignalInit<T>>,
-{
-    let scope_id = get_current_scope()
-        .ok_or(SignalCreationError::OutsideScope)
-        .unwrap(); // safe, we want to panic if not in scope
-    let signal_id = get_next_signal_id_for_scope(scope_id);
-    let signal = Signal {
-        id: (scope_id, signal_id),
-        _marker: PhantomData,
-    };
+impl<T> Copy for ReadSignal<T> {}
 
-    {
-        let mut signals = SIGNALS.lock();
-        if signals.get(&signal.id).is_none() {
-            let initial_value = match init.into() {
-                SignalInit::Value(v) => v,
-                SignalInit::InitFn(f) => f(),
-            };
-            signals.insert(
-                signal.id,
-                StoredValue {
-                    value: Box::new(initial_value),
-                },
-            );
-        }
+impl<T> Clone for WriteSignal<T> {
+    fn clone(&self) -> Self {
+        *self
     }
-
-    signal
 }
 
-//==============================================================================
-// EFFECTS
-//==============================================================================
+impl<T> Copy for WriteSignal<T> {}
 
-#[derive(Clone, Copy, Debug)]
-struct Effect {
-    id: (usize, usize),
+impl<T> From<Signal<T>> for ReadSignal<T> {
+    fn from(signal: Signal<T>) -> Self {
+        ReadSignal { signal }
+    }
 }
 
-/// Create effect that runs when dependencies change
-pub fn create_effect(effect: impl Fn() + Send + 'static) {
-    let scope_id = get_current_scope()
-        .ok_or(SignalCreationError::OutsideScope)
-        .unwrap(); // safe, we want to panic if not in scope
-    let effect_id = get_next_effect_id_for_scope(scope_id);
-    let effect_struct = Effect {
-        id: (scope_id, effect_id),
-    };
+impl<T> From<Signal<T>> for WriteSignal<T> {
+    fn from(signal: Signal<T>) -> Self {
+        WriteSignal { signal }
+    }
+}
 
-    {
-        let mut effects = SCOPE_EFFECTS.lock();
-        effects.insert(effect_struct.id, Box::new(effect));
+impl<T> Signal<T> {
+    /// Splits the signal into a handle that can only read it and one that
+    /// can only write it
+    pub fn split(self) -> (ReadSignal<T>, WriteSignal<T>) {
+        (self.into(), self.into())
     }
 }
 
-//==============================================================================
-// SCOPE MANAGEMENT
-//==============================================================================
+impl<T: 'static> ReadSignal<T> {
+    /// Access signal value immutably
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.signal.with(f)
+    }
 
-/// Run function within new reactive scope
-pub(crate) fn run_scope(
-    scope_fn: impl FnMut() -> Node + Send + 'static,
-    callback: impl Fn(&Node) + Send + Sync + 'static,
-) -> Node {
-    let scope_id = {
-        let mut next_id = NEXT_SCOPE_ID.lock();
-        let current = *next_id;
-        *next_id = current + 1;
-        current
-    };
+    /// Access signal value immutably, or tell why it can't be
+    pub fn try_with<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, SignalError> {
+        self.signal.try_with(f)
+    }
 
+    /// Get cloned value
+    pub fn get(&self) -> T
+    where
+        T: Clone,
     {
-        let mut scope_functions = SCOPE_FUNCTIONS.lock();
-        scope_functions.insert(scope_id, Box::new(scope_fn));
+        self.signal.get()
     }
 
+    /// Get cloned value, or tell why it can't be
+    pub fn try_get(&self) -> Result<T, SignalError>
+    where
+        T: Clone,
     {
-        let mut scope_callbacks = SCOPE_CALLBACKS.lock();
-        scope_callbacks.insert(scope_id, Arc::new(callback));
+        self.signal.try_get()
     }
 
-    render_scope(scope_id)
-}
+    /// Access signal value immutably without the current scope depending on it
+    pub fn with_untracked<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.signal.with_untracked(f)
+    }
 
-//==============================================================================
-// INTERNAL FUNCTIONS
-//==============================================================================
+    /// Get cloned value without the current scope depending on it
+    pub fn get_untracked(&self) -> T
+    where
+        T: Clone,
+    {
+        self.signal.get_untracked()
+    }
 
-fn get_current_scope() -> Option<usize> {
-    *CURRENT_SCOPE.lock()
+    /// Same as [`ReadSignal::get_untracked`]
+    pub fn peek(&self) -> T
+    where
+        T: Clone,
+    {
+        self.signal.peek()
+    }
 }
 
-fn set_current_scope(scope_id: Option<usize>) {
-    *CURRENT_SCOPE.lock() = scope_id;
+impl<T: SignalValue + Not<Output = bool> + Clone + 'static> Not for ReadSignal<T> {
+    type Output = bool;
+    fn not(self) -> Self::Output {
+        !self.signal
+    }
 }
 
-fn get_next_signal_id_for_scope(scope_id: usize) -> usize {
-    let mut counters = SCOPE_SIGNAL_COUNTERS.lock();
-    let counter = counters.entry(scope_id).or_insert(0);
-    *counter += 1;
-    *counter
+impl<T: SignalValue + Clone + 'static> ReadSignal<T> {
+    pub fn then<R, F: FnOnce() -> R>(self, f: F) -> Option<R>
+    where
+        Signal<T>: Not<Output = bool>,
+    {
+        self.signal.then(f)
+    }
 }
 
-fn get_next_effect_id_for_scope(scope_id: usize) -> usize {
-    let mut counters = SCOPE_EFFECT_COUNTERS.lock();
-    let counter = counters.entry(scope_id).or_insert(0);
-    *counter += 1;
-    *counter
+impl<T: SignalValue + PartialEq + 'static> PartialEq<T> for ReadSignal<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.signal == *other
+    }
 }
 
-fn reset_signal_counters(scope_id: usize) {
-    SCOPE_SIGNAL_COUNTERS.lock().remove(&scope_id);
+impl<T: SignalValue + PartialOrd + Clone + 'static> PartialOrd<T> for ReadSignal<T> {
+    fn partial_cmp(&self, other: &T) -> Option<Ordering> {
+        self.signal.partial_cmp(other)
+    }
 }
 
-fn reset_effect_counters(scope_id: usize) {
-    SCOPE_EFFECT_COUNTERS.lock().remove(&scope_id);
+impl<T: SignalValue + PartialEq + Clone + 'static> PartialEq for ReadSignal<T> {
+    fn eq(&self, other: &ReadSignal<T>) -> bool {
+        self.signal == other.signal
+    }
 }
 
-struct ScopeGuard {
-    previous_scope: Option<usize>,
+impl<T: SignalValue + PartialOrd + Clone + 'static> PartialOrd for ReadSignal<T> {
+    fn partial_cmp(&self, other: &ReadSignal<T>) -> Option<Ordering> {
+        self.signal.partial_cmp(&other.signal)
+    }
 }
 
-impl Drop for ScopeGuard {
-    fn drop(&mut self) {
-        set_current_scope(self.previous_scope);
+impl<T: SignalValue + Clone + 'static> ReadSignal<Vec<T>> {
+    /// Get the length of the vector
+    pub fn len(&self) -> usize {
+        self.with(|v| v.len()).unwrap_or(0)
     }
-}
 
-fn render_scope(scope_id: usize) -> Node {
-    let _guard = ScopeGuard {
-        previous_scope: get_current_scope(),
-    };
-    set_current_scope(Some(scope_id));
+    /// Check if the vector is empty
+    pub fn is_empty(&self) -> bool {
+        self.with(|v| v.is_empty()).unwrap_or(true)
+    }
 
-    let (should_clear_deps, was_rendering) = {
-        let mut rendering_flag = RENDERING_SCOPE.lock();
-        let mut changes = SCOPE_SIGNAL_CHANGES.lock();
-        let was_rendering = *rendering_flag;
-        *rendering_flag = scope_id;
-        changes.clear();
-        (was_rendering == 0, was_rendering)
-    };
+    /// Get an iterator over the vector contents (creates a snapshot)
+    pub fn iter(&self) -> impl Iterator<Item = T> {
+        self.signal.iter()
+    }
 
-    if should_clear_deps {
-        let signal_ids = {
-            let mut scope_deps = SCOPE_DEPENDENCIES.lock();
-            scope_deps.remove(&scope_id)
-        };
+    /// Apply a function to each element and collect results
+    pub fn map<R, F>(&self, f: F) -> Vec<R>
+    where
+        F: FnMut(T) -> R,
+    {
+        self.signal.map(f)
+    }
 
-        if let Some(signal_ids) = signal_ids {
-            let mut signal_deps = SIGNAL_DEPENDENCIES.lock();
-            for signal_id in signal_ids {
-                if let Some(scopes) = signal_deps.get_mut(&signal_id) {
-                    scopes.remove(&scope_id);
-                }
-            }
-        }
+    /// Filter elements and return a new vector
+    pub fn filter<F>(&self, f: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.signal.filter(f)
     }
+}
 
-    let scope_fn = {
-        let mut scope_functions = SCOPE_FUNCTIONS.lock();
-        scope_functions.remove(&scope_id)
-    };
+impl<T: 'static> WriteSignal<T> {
+    /// Update signal value and trigger re-renders if changed
+    pub fn set(&self, value: T)
+    where
+        T: PartialEq,
+    {
+        self.signal.set(value)
+    }
 
-    let node = scope_fn.map(|mut fnc| {
-        let mut node = fnc();
-        {
-            let mut scope_functions = SCOPE_FUNCTIONS.lock();
-            scope_functions.insert(scope_id, fnc);
-        }
-        if let Some(el) = node.as_element_mut() {
-            el.key = scope_id.to_string();
-        }
-        node
-    });
+    /// Update signal value and trigger re-renders if changed, or tell why it
+    /// can't be
+    pub fn try_set(&self, value: T) -> Result<(), SignalError>
+    where
+        T: PartialEq,
+    {
+        self.signal.try_set(value)
+    }
 
-    if let Some(ref node) = node {
-        let scope_callbacks = SCOPE_CALLBACKS.lock();
-        if let Some(callback) = scope_callbacks.get(&scope_id) {
-            callback(node);
-        }
+    /// Mutate signal value in place and trigger re-renders, see
+    /// [`Signal::update`]
+    pub fn update(&self, f: impl FnOnce(&mut T))
+    where
+        T: Clone,
+    {
+        self.signal.update(f)
     }
 
-    reset_signal_counters(scope_id);
-    run_scope_effects(scope_id);
-    reset_effect_counters(scope_id);
+    /// Mutate signal value in place and trigger re-renders, returning what
+    /// `f` returns
+    pub fn with_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> R
+    where
+        T: Clone,
+    {
+        self.signal.with_mut(f)
+    }
 
-    let signal_changes = {
-        let mut changes = SCOPE_SIGNAL_CHANGES.lock();
-        let mut rendering_flag = RENDERING_SCOPE.lock();
-        let result = if !changes.is_empty() {
-            Some(core::mem::take(&mut *changes))
-        } else {
-            None
-        };
-        *rendering_flag = was_rendering;
-        result
-    };
+    /// Mutate signal value in place, triggering re-renders only if `f` returns
+    /// `true`, which is returned as well
+    pub fn update_if(&self, f: impl FnOnce(&mut T) -> bool) -> bool
+    where
+        T: Clone,
+    {
+        self.signal.update_if(f)
+    }
+}
 
-    if let Some(changes) = signal_changes {
-        {
-            let mut pending = PENDING_SCOPE_RENDERS.lock();
-            let signal_deps = SIGNAL_DEPENDENCIES.lock();
-            for signal_id in changes {
-                if let Some(dependent_scopes) = signal_deps.get(&signal_id) {
-                    pending.extend(dependent_scopes.clone());
-                }
-            }
-        }
+// write halves are told apart by the signal they write, as they can't read it
+impl<T> PartialEq for WriteSignal<T> {
+    fn eq(&self, other: &WriteSignal<T>) -> bool {
+        self.signal.id == other.signal.id && self.signal.runtime == other.signal.runtime
+    }
+}
 
-        if was_rendering == 0 {
-            process_pending_renders();
-        }
+impl<T: SignalValue + PartialEq + Clone + core::ops::Add<Output = T> + 'static> AddAssign<T>
+    for WriteSignal<T>
+{
+    fn add_assign(&mut self, rhs: T) {
+        self.signal += rhs;
     }
+}
 
-    node.unwrap_or(Node::Empty)
+impl<T: SignalValue + PartialEq + Clone + core::ops::Sub<Output = T> + 'static> SubAssign<T>
+    for WriteSignal<T>
+{
+    fn sub_assign(&mut self, rhs: T) {
+        self.signal -= rhs;
+    }
 }
 
-fn run_scope_effects(scope_id: usize) {
-    let effects = SCOPE_EFFECTS.lock();
-    effects
-        .iter()
-        .filter(|((effect_scope_id, _), _)| *effect_scope_id == scope_id)
-        .for_each(|(_, effect)| effect());
+impl<T: SignalValue + PartialEq + Clone + core::ops::Mul<Output = T> + 'static> MulAssign<T>
+    for WriteSignal<T>
+{
+    fn mul_assign(&mut self, rhs: T) {
+        self.signal *= rhs;
+    }
 }
 
-fn process_pending_renders() {
-    loop {
-        let scope_to_render = {
-            let mut pending = PENDING_SCOPE_RENDERS.lock();
-            if pending.is_empty() {
-                None
-            } else {
-                pending.iter().next().copied().map(|scope_id| {
-                    pending.remove(&scope_id);
-                    scope_id
-                })
-            }
-        };
+impl<T: SignalValue + PartialEq + Clone + core::ops::Div<Output = T> + 'static> DivAssign<T>
+    for WriteSignal<T>
+{
+    fn div_assign(&mut self, rhs: T) {
+        self.signal /= rhs;
+    }
+}
 
-        match scope_to_render {
-            Some(scope_id) => render_scope(scope_id),
-            None => break,
-        };
+impl WriteSignal<bool> {
+    /// Toggle the boolean value
+    pub fn toggle(&self) {
+        self.signal.toggle()
+    }
+
+    /// Set to true
+    pub fn turn_on(&self) {
+        self.signal.turn_on()
+    }
+
+    /// Set to false
+    pub fn turn_off(&self) {
+        self.signal.turn_off()
+    }
+}
+
+impl<T: SignalValue + PartialEq + Clone + 'static> WriteSignal<Vec<T>> {
+    /// Push an item to the vector
+    pub fn push(&self, item: T) {
+        self.signal.push(item)
+    }
+
+    /// Pop an item from the vector
+    pub fn pop(&self) -> Option<T> {
+        self.signal.pop()
+    }
+
+    /// Clear the vector
+    pub fn clear(&self) {
+        self.signal.clear()
     }
 }
 
 //==============================================================================
-// RESOURCE
+// SIGNAL CREATION
 //==============================================================================
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ResourceStatus {
-    Idle,
-    Pending,
-    Loading,
-    Resolved,
+/// Signal initialization options
+pub enum SignalInit<T> {
+    Value(T),
+    InitFn(Box<dyn Fn() -> T + Send + 'static>),
 }
 
-impl SignalValue for ResourceStatus {
-    fn as_any(&self) -> Option<&dyn Any> {
-        Some(self)
+// `PartialEq`, which signal values need anyway, keeps a `SignalInit` from
+// passing for a value itself
+impl<T: SignalValue + PartialEq> From<T> for SignalInit<T> {
+    fn from(value: T) -> Self {
+        SignalInit::Value(value)
     }
 }
 
-pub struct Resource<T> {
-    status: Signal<ResourceStatus>,
-    value: Signal<Option<T>>,
+#[derive(Debug)]
+pub enum SignalCreationError {
+    OutsideScope,
 }
 
-impl<T: SignalValue + PartialEq + 'static> Resource<T> {
-    pub fn status(&self) -> Signal<ResourceStatus> {
-        self.status
+impl core::fmt::Display for SignalCreationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Signals can only be created within a scope context")
     }
+}
 
-    pub fn get(&self) -> Option<T>
-    where
-        T: Clone,
-    {
-        self.value.get()
-    }
+impl core::error::Error for SignalCreationError {}
 
-    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
-        self.value.with(|v| v.as_ref().map(f)).unwrap_or_default()
-    }
+/// Create new signal within current scope
+#[track_caller]
+pub fn create_signal<T, I>(init: I) -> Signal<T>
+where
+    T: SignalValue + PartialEq + 'static,
+    I: Into<SignalInit<T>>,
+{
+    try_create_signal(init).unwrap() // safe, we want to panic if not in scope
+}
 
-    pub fn retry(&self) {
-        self.status.set(ResourceStatus::Pending);
-    }
+/// Create a signal within the current scope and hand out its read and write
+/// halves separately, so that passing one of them on only grants reading or
+/// writing the value.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[derive(PartialEq)]
+/// struct CounterProps {
+///     count: ReadSignal<i32>,
+///     set_count: WriteSignal<i32>,
+/// }
+///
+/// #[component]
+/// fn Counter(CounterProps { count, set_count }: &CounterProps) -> Node {
+///     let mut set_count = *set_count;
+///     set_count += 1;
+///     rsx!(<p>{*count}</p>)
+/// }
+///
+/// #[component]
+/// fn App() -> Node {
+///     let (count, set_count) = create_signal_pair(0);
+///     rsx!(<Counter count={count} set_count={set_count} />)
+/// }
+///
+/// assert_eq!(rsx!(<App />).to_string(), "<p>1</p>");
+/// ```
+#[track_caller]
+pub fn create_signal_pair<T, I>(init: I) -> (ReadSignal<T>, WriteSignal<T>)
+where
+    T: SignalValue + PartialEq + 'static,
+    I: Into<SignalInit<T>>,
+{
+    create_signal(init).split()
 }
 
-#[allow(unused_variables)]
-/// Create a resource that can be asynchronously loaded
-pub fn create_resource<T, F>(fetcher: F) -> Resource<T>
+/// Create new signal within current scope, or fail when there is no scope,
+/// such as outside of components and [`create_root`]
+#[track_caller]
+pub fn try_create_signal<T, I>(init: I) -> Result<Signal<T>, SignalCreationError>
 where
     T: SignalValue + PartialEq + 'static,
-    F: AsyncFn() -> T + Send + Clone + 'static,
-    Option<T>: Copy,
+    I: Into<SignalInit<T>>,
 {
-    let value = create_signal(None);
-    let status = create_signal(ResourceStatus::Idle);
+    scope_signal(|| match init.into() {
+        SignalInit::Value(v) => v,
+        SignalInit::InitFn(f) => f(),
+    })
+}
 
-    create_effect(move || {
-        if status.get() == ResourceStatus::Idle || status.get() == ResourceStatus::Pending {
-            status.set(ResourceStatus::Loading);
+/// Create new signal within current scope for a value that isn't `Send`, such
+/// as an `Rc`, a DOM node or a `JsValue`.
+///
+/// The signal handle can still be moved into component functions, effects and
+/// event handlers, but using it from another thread than the one it was created
+/// on panics.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+/// use std::rc::Rc;
+///
+/// #[component]
+/// fn Shared() -> Node {
+///     let shared = create_local_signal(Rc::new(1));
+///     create_effect(move || {
+///         let _ = shared.with(|value| Rc::strong_count(value));
+///     });
+///     rsx!(<p>{move || *shared.get()}</p>)
+/// }
+/// ```
+#[track_caller]
+pub fn create_local_signal<T: PartialEq + 'static>(value: T) -> Signal<T> {
+    scope_signal(|| value).unwrap() // safe, we want to panic if not in scope
+}
 
-            #[cfg(feature = "wasm")]
-            let fetcher = fetcher.clone();
-            #[cfg(feature = "wasm")]
-            let value = value.clone();
+/// The signal at the next position of the current scope, created with `init`
+/// on its first render
+#[track_caller]
+fn scope_signal<T: 'static>(init: impl FnOnce() -> T) -> Result<Signal<T>, SignalCreationError> {
+    let rt = runtime();
+    let scope_id = get_current_scope().ok_or(SignalCreationError::OutsideScope)?;
+    #[cfg(debug_assertions)]
+    check_hook(scope_id, "signal", core::any::type_name::<T>());
+    let existing = with_scope(scope_id, |scope| {
+        let position = scope.signal_cursor;
+        scope.signal_cursor += 1;
+        scope.signals.get(position).copied()
+    })
+    .flatten();
 
-            #[cfg(feature = "wasm")]
-            wasm_bindgen_futures::spawn_local(async move {
-                let val = fetcher().await;
-                value.set(Some(val));
-                status.set(ResourceStatus::Resolved);
+    let id = match existing {
+        Some(id) => id,
+        None => {
+            let initial_value = init();
+            let id = rt.signals.borrow_mut().insert(SignalState {
+                value: Rc::new(initial_value),
+                subscribers: BTreeSet::new(),
+                on_dispose: Vec::new(),
+                owner: scope_id,
             });
+            with_scope(scope_id, |scope| scope.signals.push(id));
+            id
         }
-    });
+    };
 
-    Resource { status, value }
+    Ok(Signal::from_key(id))
 }
 
 //==============================================================================
-// TESTS
+// EFFECTS
 //==============================================================================
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use alloc::sync::Arc;
-    use core::sync::atomic::{AtomicUsize, Ordering};
+/// What an effect returns: nothing, or a closure cleaning up after it
+pub trait EffectCleanup {
+    fn into_cleanup(self) -> Option<Box<dyn FnOnce() + Send>>;
+}
 
-    #[test]
-    fn test_nested_scopes() {
+impl EffectCleanup for () {
+    fn into_cleanup(self) -> Option<Box<dyn FnOnce() + Send>> {
+        None
+    }
+}
+
+impl<F: FnOnce() + Send + 'static> EffectCleanup for F {
+    fn into_cleanup(self) -> Option<Box<dyn FnOnce() + Send>> {
+        Some(Box::new(self))
+    }
+}
+
+/// Marks the child scopes effects run in
+struct Effect;
+
+/// Create effect that runs when dependencies change.
+///
+/// The effect first runs once the current scope has rendered, and then again
+/// whenever a signal it read changes, whether or not the scope renders again.
+/// Renders of the scope keep the effect created at the same position last
+/// time, swapping in the new closure without running it.
+///
+/// An effect may return a closure, which runs before the effect runs again
+/// and once the effect is disposed along with its scope.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Title() -> Node {
+///     let count = create_signal(0);
+///     create_effect(move || {
+///         // runs again whenever `count` changes
+///         let _title = format!("Clicked {} times", count.get());
+///         move || {
+///             // runs before the next change is applied
+///         }
+///     });
+///     rsx!(<p>{count}</p>)
+/// }
+/// ```
+#[track_caller]
+pub fn create_effect<R: EffectCleanup>(effect: impl Fn() -> R + Send + 'static) {
+    let parent = get_current_scope()
+        .ok_or(SignalCreationError::OutsideScope)
+        .unwrap(); // safe, we want to panic if not in scope
+    #[cfg(debug_assertions)]
+    check_hook(parent, "effect", "");
+    let matched = with_scope(parent, |scope| {
+        scope.matched_children.take(TypeId::of::<Effect>(), None)
+    });
+    let reused = matched.as_ref().and_then(|(_, reused)| *reused);
+    let scope_id = reused.unwrap_or_else(create_scope);
+    own_scope(scope_id);
+
+    let function: ScopeFn = Box::new(move || {
+        if let Some(cleanup) = effect().into_cleanup() {
+            on_cleanup(cleanup);
+        }
+        Node::Empty
+    });
+    with_scope(parent, |scope| {
+        if let Some((id, _)) = matched {
+            scope.matched_children.current.insert(id, scope_id);
+        }
+        if reused.is_none() {
+            scope.new_effects.push(scope_id);
+        }
+    });
+    let replaced = with_scope(scope_id, |scope| scope.function.replace(function));
+    // the closure from the last render is dropped outside of the borrow
+    drop(replaced);
+}
+
+/// Registers a hook to run when the current scope is disposed, or before it
+/// renders again.
+///
+/// Scopes are disposed when the scope that created them renders without
+/// creating them again, such as a component that is no longer shown. This is
+/// the place to release anything a component set up, like timers or sockets.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Clock() -> Node {
+///     on_cleanup(|| {
+///         // stop ticking
+///     });
+///     rsx!(<p>Tick</p>)
+/// }
+/// ```
+pub fn on_cleanup(cleanup: impl FnOnce() + Send + 'static) {
+    let scope_id = get_current_scope()
+        .ok_or(SignalCreationError::OutsideScope)
+        .unwrap(); // safe, we want to panic if not in scope
+    with_scope(scope_id, |scope| scope.cleanups.push(Box::new(cleanup)));
+}
+
+//==============================================================================
+// MEMOS
+//==============================================================================
+
+/// Read-only value derived from signals, see [`create_memo`]
+#[derive(Debug)]
+pub struct Memo<T> {
+    scope: ScopeId,
+    runtime: RuntimeId,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for Memo<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Memo<T> {}
+
+impl<T: SignalValue + PartialEq + 'static> Memo<T> {
+    /// Access the memoized value immutably, computing it first if an input
+    /// changed since it was last computed
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        match self.try_with(f) {
+            Ok(result) => Some(result),
+            Err(SignalError::TypeMismatch) => None,
+            Err(error) => self.fail(error),
+        }
+    }
+
+    /// Access the memoized value immutably, or tell why it can't be
+    pub fn try_with<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, SignalError> {
+        // scopes of another runtime may sit at the same slot
+        if runtime().id != self.runtime {
+            return Err(SignalError::MissingRuntime);
+        }
+        let (stale, value) = with_scope(self.scope, |scope| (scope.stale, scope.memo))
+            .ok_or(SignalError::Disposed)?;
+        if unschedule(self.scope) || stale || value.is_none() {
+            render_scope(self.scope);
+        }
+
+        let id = with_scope(self.scope, |scope| scope.memo)
+            .flatten()
+            .ok_or(SignalError::Disposed)?;
+        Signal::<T>::from_key(id).try_with(f)
+    }
+
+    /// Get cloned value
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.try_get().unwrap_or_else(|error| self.fail(error))
+    }
+
+    /// Get cloned value, or tell why it can't be
+    pub fn try_get(&self) -> Result<T, SignalError>
+    where
+        T: Clone,
+    {
+        self.try_with(T::clone)
+    }
+
+    /// Panics with `error`, naming the scope the memo is computed in
+    fn fail(&self, error: SignalError) -> ! {
+        panic!("{error} (memo {:?})", self.scope)
+    }
+}
+
+impl<T: SignalValue + PartialEq + 'static> PartialEq<T> for Memo<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.with(|val| val == other).unwrap_or(false)
+    }
+}
+
+/// Marks the child scopes memos are computed in
+struct MemoComputation;
+
+/// Create a value derived from signals, which is only computed again when one
+/// of the signals it reads changes.
+///
+/// The value is computed lazily, the first time it's read and then whenever an
+/// input changed since, though a memo that is read by a scope or an effect is
+/// computed right away so that they only re-run when its value changes as far
+/// as `PartialEq` can tell. Renders of the current scope keep the memo created
+/// at the same position last time, swapping in the new closure.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Total() -> Node {
+///     let prices = create_signal(vec![3, 4]);
+///     let total = create_memo(move || prices.with(|prices| prices.iter().sum::<i32>()).unwrap());
+///     rsx!(<p>{total}</p>)
+/// }
+///
+/// assert_eq!(rsx!(<Total />).to_string(), "<p>7</p>");
+/// ```
+#[track_caller]
+pub fn create_memo<T, F>(compute: F) -> Memo<T>
+where
+    T: SignalValue + PartialEq + 'static,
+    F: Fn() -> T + Send + 'static,
+{
+    let parent = get_current_scope()
+        .ok_or(SignalCreationError::OutsideScope)
+        .unwrap(); // safe, we want to panic if not in scope
+    #[cfg(debug_assertions)]
+    check_hook(parent, "memo", core::any::type_name::<T>());
+    let matched = with_scope(parent, |scope| {
+        scope
+            .matched_children
+            .take(TypeId::of::<MemoComputation>(), None)
+    });
+    let reused = matched.as_ref().and_then(|(_, reused)| *reused);
+    let scope_id = reused.unwrap_or_else(create_scope);
+    own_scope(scope_id);
+    if let Some((id, _)) = matched {
+        with_scope(parent, |scope| {
+            scope.matched_children.current.insert(id, scope_id)
+        });
+    }
+
+    let function: ScopeFn = Box::new(move || {
+        let output = compute();
+        match with_scope(scope_id, |scope| scope.memo).flatten() {
+            Some(id) => Signal::from_key(id).set(output),
+            None => {
+                let value = create_signal(output);
+                with_scope(scope_id, |scope| scope.memo = Some(value.id));
+            }
+        }
+        Node::Empty
+    });
+    let replaced = with_scope(scope_id, |scope| {
+        scope.stale |= reused.is_none();
+        scope.function.replace(function)
+    });
+    // the closure from the last render is dropped outside of the borrow
+    drop(replaced);
+
+    Memo {
+        scope: scope_id,
+        runtime: runtime().id,
+        _marker: PhantomData,
+    }
+}
+
+/// Marks a memo nothing reads as stale rather than computing it, it is
+/// computed once it's read again
+fn defer_memo(scope_id: ScopeId) -> bool {
+    let Some(Some(id)) = with_scope(scope_id, |scope| scope.memo) else {
+        return false;
+    };
+    let unread = runtime()
+        .signals
+        .borrow()
+        .get(id)
+        .is_none_or(|signal| signal.subscribers.is_empty());
+    if unread {
+        with_scope(scope_id, |scope| scope.stale = true);
+    }
+    unread
+}
+
+//==============================================================================
+// STORES
+//==============================================================================
+
+/// Structs whose fields are each kept in a signal of their own, implemented
+/// with `#[derive(Store)]`
+pub trait Store {
+    /// The handle giving access to the signal of each field
+    type Handle: Copy;
+
+    /// Creates a signal in the current scope for each field
+    fn into_handle(self) -> Self::Handle;
+}
+
+/// Create a store within the current scope, with a signal for each field of
+/// `value`, so that scopes only depend on the fields they read.
+///
+/// Like signals, a store is created once per scope, and later renders get
+/// back the same signals. See the `Store` derive for an example.
+#[track_caller]
+pub fn create_store<T: Store>(value: T) -> T::Handle {
+    value.into_handle()
+}
+
+//==============================================================================
+// COLLECTIONS
+//==============================================================================
+
+/// How many operations a collection keeps for readers that are catching up,
+/// readers that fell further behind start over from the whole collection
+const DIFF_LOG_LIMIT: usize = 256;
+
+/// A collection along with the last operations applied to it, so that readers
+/// can follow along with what changed rather than comparing snapshots
+#[derive(Clone)]
+struct DiffLog<C, D> {
+    value: C,
+    diffs: VecDeque<D>,
+    /// Number of operations applied since the collection was created
+    version: usize,
+}
+
+impl<C, D: Clone> DiffLog<C, D> {
+    fn new(value: C) -> Self {
+        DiffLog {
+            value,
+            diffs: VecDeque::new(),
+            version: 0,
+        }
+    }
+
+    fn record(&mut self, diff: D) {
+        self.diffs.push_back(diff);
+        if self.diffs.len() > DIFF_LOG_LIMIT {
+            self.diffs.pop_front();
+        }
+        self.version += 1;
+    }
+
+    /// The operations applied after `version`, or `None` if they are no
+    /// longer all kept
+    fn since(&self, version: usize) -> Option<Vec<D>> {
+        let oldest = self.version - self.diffs.len();
+        if version < oldest || version > self.version {
+            return None;
+        }
+        Some(self.diffs.range(version - oldest..).cloned().collect())
+    }
+}
+
+/// An operation applied to a [`SignalVec`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum VecDiff<T> {
+    /// `value` was inserted at `index`, shifting the items after it
+    InsertAt { index: usize, value: T },
+    /// The item at `index` was removed, shifting the items after it
+    RemoveAt { index: usize },
+    /// The item at `from` was removed and inserted back at `to`
+    Move { from: usize, to: usize },
+    /// The item at `index` was replaced with `value`
+    UpdateAt { index: usize, value: T },
+    /// All items were removed
+    Clear,
+}
+
+/// A vector in a signal that records each operation applied to it, see
+/// [`create_signal_vec`]
+pub struct SignalVec<T> {
+    log: Signal<DiffLog<Vec<T>, VecDiff<T>>>,
+}
+
+impl<T> Clone for SignalVec<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SignalVec<T> {}
+
+impl<T> core::fmt::Debug for SignalVec<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SignalVec")
+            .field("id", &self.log.id)
+            .finish()
+    }
+}
+
+impl<T> PartialEq for SignalVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.log.id == other.log.id
+    }
+}
+
+/// Create a vector within the current scope which, unlike a `Signal<Vec<T>>`,
+/// tells its readers which items were inserted, removed, moved or updated.
+///
+/// `<For>` uses this to render only the rows that changed, and the views from
+/// [`SignalVec::map`], [`SignalVec::filter`] and [`SignalVec::sort_by`] apply
+/// the same operations to their own items rather than starting over.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[component]
+/// fn Todos() -> Node {
+///     let todos = create_signal_vec(vec!["write docs", "fix tests"]);
+///     let sorted = todos.sort_by(|a, b| a.cmp(b));
+///     rsx!(
+///         <ul>
+///             <For each={sorted} key={|todo: &&str| todo.to_string()}>
+///                 {|todo: &&str| rsx!(<li>{*todo}</li>)}
+///             </For>
+///         </ul>
+///     )
+/// }
+///
+/// assert_eq!(
+///     rsx!(<Todos />).to_string(),
+///     "<ul><li>fix tests</li><li>write docs</li></ul>"
+/// );
+/// ```
+#[track_caller]
+pub fn create_signal_vec<T: SignalValue + Clone>(items: Vec<T>) -> SignalVec<T> {
+    SignalVec {
+        log: scope_signal(|| DiffLog::new(items)).unwrap(), // safe, we want to panic if not in scope
+    }
+}
+
+impl<T: SignalValue + Clone> SignalVec<T> {
+    /// Access the items immutably
+    pub fn with<R>(&self, f: impl FnOnce(&[T]) -> R) -> R {
+        self.log.read(|log| f(&log.value))
+    }
+
+    /// Get cloned items
+    pub fn get(&self) -> Vec<T> {
+        self.with(|items| items.to_vec())
+    }
+
+    /// Get the number of items
+    pub fn len(&self) -> usize {
+        self.with(|items| items.len())
+    }
+
+    /// Check if there are no items
+    pub fn is_empty(&self) -> bool {
+        self.with(|items| items.is_empty())
+    }
+
+    /// Number of operations applied to the vector so far
+    pub fn version(&self) -> usize {
+        self.log.read(|log| log.version)
+    }
+
+    /// The operations applied after `version`, or `None` when some of them
+    /// are no longer kept and the items should be read again as a whole
+    pub fn diffs_since(&self, version: usize) -> Option<Vec<VecDiff<T>>> {
+        self.log.read(|log| log.since(version))
+    }
+
+    /// Add an item at the end
+    pub fn push(&self, value: T) {
+        self.log.update(|log| {
+            let index = log.value.len();
+            log.value.push(value.clone());
+            log.record(VecDiff::InsertAt { index, value });
+        });
+    }
+
+    /// Remove the last item
+    pub fn pop(&self) -> Option<T> {
+        let index = self.log.with_untracked(|log| log.value.len())?;
+        index.checked_sub(1).map(|index| self.remove(index))
+    }
+
+    /// Insert an item at `index`, shifting the items after it.
+    ///
+    /// Panics if `index` is out of bounds, like [`Vec::insert`].
+    pub fn insert(&self, index: usize, value: T) {
+        self.log.update(|log| {
+            log.value.insert(index, value.clone());
+            log.record(VecDiff::InsertAt { index, value });
+        });
+    }
+
+    /// Remove the item at `index`, shifting the items after it.
+    ///
+    /// Panics if `index` is out of bounds, like [`Vec::remove`].
+    pub fn remove(&self, index: usize) -> T {
+        self.log.with_mut(|log| {
+            let value = log.value.remove(index);
+            log.record(VecDiff::RemoveAt { index });
+            value
+        })
+    }
+
+    /// Move the item at `from` so that it ends up at `to`.
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn move_item(&self, from: usize, to: usize) {
+        self.log.update_if(|log| {
+            // checked up front, so a failed move leaves every item in place
+            let len = log.value.len();
+            assert!(
+                from < len && to < len,
+                "cannot move item {from} to {to} in a vector of {len} items"
+            );
+            let value = log.value.remove(from);
+            log.value.insert(to, value);
+            if from == to {
+                return false;
+            }
+            log.record(VecDiff::Move { from, to });
+            true
+        });
+    }
+
+    /// Replace the item at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_at(&self, index: usize, value: T) {
+        self.update_at(index, |item| *item = value);
+    }
+
+    /// Mutate the item at `index` in place.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn update_at(&self, index: usize, f: impl FnOnce(&mut T)) {
+        self.log.update(|log| {
+            f(&mut log.value[index]);
+            let value = log.value[index].clone();
+            log.record(VecDiff::UpdateAt { index, value });
+        });
+    }
+
+    /// Remove all items
+    pub fn clear(&self) {
+        self.log.update_if(|log| {
+            if log.value.is_empty() {
+                return false;
+            }
+            log.value.clear();
+            log.record(VecDiff::Clear);
+            true
+        });
+    }
+
+    /// A vector of `f` applied to each item, which is kept up to date by
+    /// applying `f` to the items that change only.
+    ///
+    /// Like memos, views are created once per scope.
+    #[track_caller]
+    pub fn map<R, F>(&self, f: F) -> SignalVec<R>
+    where
+        R: SignalValue + Clone,
+        F: Fn(&T) -> R + Send + 'static,
+    {
+        self.view(move |_: &mut (), target, diff| match diff {
+            VecDiff::InsertAt { index, value } => target.insert(index, f(&value)),
+            VecDiff::RemoveAt { index } => target.remove(index),
+            VecDiff::Move { from, to } => target.move_item(from, to),
+            VecDiff::UpdateAt { index, value } => target.update_at(index, f(&value)),
+            VecDiff::Clear => target.clear(),
+        })
+    }
+
+    /// A vector of the items for which `predicate` returns `true`, which is
+    /// kept up to date by checking the items that change only.
+    ///
+    /// Like memos, views are created once per scope.
+    #[track_caller]
+    pub fn filter<F>(&self, predicate: F) -> SignalVec<T>
+    where
+        F: Fn(&T) -> bool + Send + 'static,
+    {
+        // `kept` has whether each source item is kept
+        self.view(move |kept: &mut Vec<bool>, target, diff| {
+            let position =
+                |kept: &[bool], index: usize| kept[..index].iter().filter(|kept| **kept).count();
+            match diff {
+                VecDiff::InsertAt { index, value } => {
+                    let keep = predicate(&value);
+                    kept.insert(index, keep);
+                    if keep {
+                        target.insert(position(kept, index), value)
+                    }
+                }
+                VecDiff::RemoveAt { index } => {
+                    if kept.remove(index) {
+                        target.remove(position(kept, index))
+                    }
+                }
+                VecDiff::Move { from, to } => {
+                    let keep = kept.remove(from);
+                    let old = position(kept, from);
+                    kept.insert(to, keep);
+                    if keep {
+                        target.move_item(old, position(kept, to))
+                    }
+                }
+                VecDiff::UpdateAt { index, value } => {
+                    let (was_kept, keep) = (kept[index], predicate(&value));
+                    kept[index] = keep;
+                    let at = position(kept, index);
+                    match (was_kept, keep) {
+                        (true, true) => target.update_at(at, value),
+                        (true, false) => target.remove(at),
+                        (false, true) => target.insert(at, value),
+                        (false, false) => {}
+                    }
+                }
+                VecDiff::Clear => {
+                    kept.clear();
+                    target.clear()
+                }
+            }
+        })
+    }
+
+    /// A vector of the items sorted with `compare`, which is kept up to date
+    /// by moving the items that change only.
+    ///
+    /// Items that compare equal keep their order of insertion. Like memos,
+    /// views are created once per scope.
+    #[track_caller]
+    pub fn sort_by<F>(&self, compare: F) -> SignalVec<T>
+    where
+        F: Fn(&T, &T) -> Ordering + Send + 'static,
+    {
+        // `sources` has the source index of each sorted item
+        self.view(move |sources: &mut Vec<usize>, target, diff| {
+            let sorted_position = |target: &ViewTarget<T>, value: &T| {
+                target
+                    .items
+                    .partition_point(|item| compare(item, value) != Ordering::Greater)
+            };
+            match diff {
+                VecDiff::InsertAt { index, value } => {
+                    sources
+                        .iter_mut()
+                        .filter(|source| **source >= index)
+                        .for_each(|source| *source += 1);
+                    let at = sorted_position(target, &value);
+                    sources.insert(at, index);
+                    target.insert(at, value)
+                }
+                VecDiff::RemoveAt { index } => {
+                    let at = sources.iter().position(|source| *source == index);
+                    sources.retain(|source| *source != index);
+                    sources
+                        .iter_mut()
+                        .filter(|source| **source > index)
+                        .for_each(|source| *source -= 1);
+                    if let Some(at) = at {
+                        target.remove(at)
+                    }
+                }
+                VecDiff::Move { from, to } => {
+                    // the order of the sorted items doesn't depend on the source
+                    for source in sources.iter_mut() {
+                        *source = match *source {
+                            source if source == from => to,
+                            source if from < to && source > from && source <= to => source - 1,
+                            source if to < from && source >= to && source < from => source + 1,
+                            source => source,
+                        }
+                    }
+                }
+                VecDiff::UpdateAt { index, value } => {
+                    let Some(old) = sources.iter().position(|source| *source == index) else {
+                        return;
+                    };
+                    sources.remove(old);
+                    let removed = target.items.remove(old);
+                    let at = sorted_position(target, &value);
+                    target.items.insert(old, removed);
+                    sources.insert(at, index);
+                    target.move_item(old, at);
+                    target.update_at(at, value)
+                }
+                VecDiff::Clear => {
+                    sources.clear();
+                    target.clear()
+                }
+            }
+        })
+    }
+
+    /// A vector derived from this one by `apply`, which gets each operation
+    /// applied here since it last ran, along with bookkeeping of its own
+    #[track_caller]
+    fn view<R: SignalValue + Clone, S: Default + Clone + 'static>(
+        &self,
+        apply: impl Fn(&mut S, &mut ViewTarget<R>, VecDiff<T>) + Send + 'static,
+    ) -> SignalVec<R> {
+        let source = *self;
+        let target = create_signal_vec(Vec::new());
+        let state = scope_signal(|| ViewState {
+            seen: None,
+            view: S::default(),
+        })
+        .unwrap(); // safe, `create_signal_vec` checked the scope
+
+        let sync = move || {
+            let seen = state.with_untracked(|state| state.seen).flatten();
+            let (diffs, version) = source.log.read(|log| {
+                // new views and views that fell behind replay the whole vector
+                let diffs = seen.and_then(|seen| log.since(seen)).unwrap_or_else(|| {
+                    let inserts =
+                        log.value
+                            .iter()
+                            .enumerate()
+                            .map(|(index, value)| VecDiff::InsertAt {
+                                index,
+                                value: value.clone(),
+                            });
+                    core::iter::once(VecDiff::Clear).chain(inserts).collect()
+                });
+                (diffs, log.version)
+            });
+            if diffs.is_empty() {
+                return;
+            }
+            state.update(|state| {
+                state.seen = Some(version);
+                target.log.update_if(|log| {
+                    let mut target = ViewTarget {
+                        items: core::mem::take(&mut log.value),
+                        diffs: Vec::new(),
+                    };
+                    diffs
+                        .into_iter()
+                        .for_each(|diff| apply(&mut state.view, &mut target, diff));
+                    log.value = target.items;
+                    let changed = !target.diffs.is_empty();
+                    target.diffs.into_iter().for_each(|diff| log.record(diff));
+                    changed
+                });
+            });
+        };
+        untrack(&sync);
+        create_effect(sync);
+        target
+    }
+}
+
+/// Bookkeeping of a view derived from a [`SignalVec`]
+#[derive(Clone)]
+struct ViewState<S> {
+    /// The version of the source vector applied so far
+    seen: Option<usize>,
+    view: S,
+}
+
+/// The items of a derived view being brought up to date, along with the
+/// operations that did so
+struct ViewTarget<T> {
+    items: Vec<T>,
+    diffs: Vec<VecDiff<T>>,
+}
+
+impl<T: Clone> ViewTarget<T> {
+    fn insert(&mut self, index: usize, value: T) {
+        self.items.insert(index, value.clone());
+        self.diffs.push(VecDiff::InsertAt { index, value });
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.items.remove(index);
+        self.diffs.push(VecDiff::RemoveAt { index });
+    }
+
+    fn move_item(&mut self, from: usize, to: usize) {
+        if from != to {
+            let value = self.items.remove(from);
+            self.items.insert(to, value);
+            self.diffs.push(VecDiff::Move { from, to });
+        }
+    }
+
+    fn update_at(&mut self, index: usize, value: T) {
+        self.items[index] = value.clone();
+        self.diffs.push(VecDiff::UpdateAt { index, value });
+    }
+
+    fn clear(&mut self) {
+        if !self.items.is_empty() {
+            self.items.clear();
+            self.diffs.push(VecDiff::Clear);
+        }
+    }
+}
+
+/// An operation applied to a [`SignalMap`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapDiff<K, V> {
+    /// `key` was inserted with `value`
+    Insert { key: K, value: V },
+    /// The value of `key` was removed
+    Remove { key: K },
+    /// The value of `key` was replaced with `value`
+    Update { key: K, value: V },
+    /// All entries were removed
+    Clear,
+}
+
+/// A map in a signal that records each operation applied to it, see
+/// [`create_signal_map`]
+pub struct SignalMap<K, V> {
+    log: Signal<DiffLog<BTreeMap<K, V>, MapDiff<K, V>>>,
+}
+
+impl<K, V> Clone for SignalMap<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for SignalMap<K, V> {}
+
+impl<K, V> core::fmt::Debug for SignalMap<K, V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SignalMap")
+            .field("id", &self.log.id)
+            .finish()
+    }
+}
+
+impl<K, V> PartialEq for SignalMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.log.id == other.log.id
+    }
+}
+
+/// Create a map within the current scope which tells its readers which
+/// entries were inserted, removed or updated, like [`create_signal_vec`] does
+/// for vectors.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+/// use momenta::signals::MapDiff;
+///
+/// let (_root, scores) = create_root(|| create_signal_map(Default::default()));
+/// let version = scores.version();
+/// scores.insert("ada", 3);
+/// scores.update("ada", |score| *score += 1);
+/// assert_eq!(scores.get(&"ada"), Some(4));
+/// assert_eq!(
+///     scores.diffs_since(version),
+///     Some(vec![
+///         MapDiff::Insert { key: "ada", value: 3 },
+///         MapDiff::Update { key: "ada", value: 4 },
+///     ])
+/// );
+/// ```
+#[track_caller]
+pub fn create_signal_map<K, V>(entries: BTreeMap<K, V>) -> SignalMap<K, V>
+where
+    K: SignalValue + Ord + Clone,
+    V: SignalValue + Clone,
+{
+    SignalMap {
+        log: scope_signal(|| DiffLog::new(entries)).unwrap(), // safe, we want to panic if not in scope
+    }
+}
+
+impl<K, V> SignalMap<K, V>
+where
+    K: SignalValue + Ord + Clone,
+    V: SignalValue + Clone,
+{
+    /// Access the entries immutably
+    pub fn with<R>(&self, f: impl FnOnce(&BTreeMap<K, V>) -> R) -> R {
+        self.log.read(|log| f(&log.value))
+    }
+
+    /// Get the cloned value of `key`
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.with(|entries| entries.get(key).cloned())
+    }
+
+    /// Check if there is a value for `key`
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.with(|entries| entries.contains_key(key))
+    }
+
+    /// Get the number of entries
+    pub fn len(&self) -> usize {
+        self.with(|entries| entries.len())
+    }
+
+    /// Check if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.with(|entries| entries.is_empty())
+    }
+
+    /// Number of operations applied to the map so far
+    pub fn version(&self) -> usize {
+        self.log.read(|log| log.version)
+    }
+
+    /// The operations applied after `version`, or `None` when some of them
+    /// are no longer kept and the entries should be read again as a whole
+    pub fn diffs_since(&self, version: usize) -> Option<Vec<MapDiff<K, V>>> {
+        self.log.read(|log| log.since(version))
+    }
+
+    /// Set the value of `key`, returning the one it replaces
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.log.with_mut(|log| {
+            let previous = log.value.insert(key.clone(), value.clone());
+            log.record(match previous {
+                Some(_) => MapDiff::Update { key, value },
+                None => MapDiff::Insert { key, value },
+            });
+            previous
+        })
+    }
+
+    /// Remove the value of `key`
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let mut removed = None;
+        self.log.update_if(|log| {
+            removed = log.value.remove(key);
+            if removed.is_some() {
+                log.record(MapDiff::Remove { key: key.clone() });
+            }
+            removed.is_some()
+        });
+        removed
+    }
+
+    /// Mutate the value of `key` in place, returning whether there was one
+    pub fn update(&self, key: K, f: impl FnOnce(&mut V)) -> bool {
+        self.log.update_if(|log| {
+            let Some(value) = log.value.get_mut(&key) else {
+                return false;
+            };
+            f(value);
+            let value = value.clone();
+            log.record(MapDiff::Update { key, value });
+            true
+        })
+    }
+
+    /// Remove all entries
+    pub fn clear(&self) {
+        self.log.update_if(|log| {
+            if log.value.is_empty() {
+                return false;
+            }
+            log.value.clear();
+            log.record(MapDiff::Clear);
+            true
+        });
+    }
+}
+
+//==============================================================================
+// ROOTS
+//==============================================================================
+
+/// Owner of signals and effects created outside of components, see
+/// [`create_root`].
+///
+/// Dropping it disposes everything it owns, running their cleanups.
+pub struct Root {
+    scope: ScopeId,
+    runtime: Runtime,
+}
+
+impl Root {
+    /// Runs `f` within the root, so the signals and effects it creates are
+    /// owned by the root as well
+    #[track_caller]
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.runtime.run(|| {
+            let result = {
+                let _guard = ScopeGuard {
+                    previous_scope: get_current_scope(),
+                };
+                set_current_scope(Some(self.scope));
+                // the root never renders, so it has nothing to depend on
+                untrack(f)
+            };
+            run_scope_effects(self.scope);
+            result
+        })
+    }
+}
+
+impl Drop for Root {
+    fn drop(&mut self) {
+        self.runtime.run(|| dispose_scope(self.scope));
+    }
+}
+
+/// Runs `f` within a new root, which owns the signals and effects it creates
+/// until it is dropped.
+///
+/// This is where state living outside of components goes, such as global app
+/// state, services, or the state of a test. Components reading the signals of
+/// a root render again when they change, like with any other signal. Roots
+/// aren't owned by the current scope, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// let (root, count) = create_root(|| create_signal(0));
+/// let html = move || rsx!(<p>{count}</p>).to_string();
+///
+/// count.set(1);
+/// assert_eq!(html(), "<p>1</p>");
+/// // the signal goes along with its root
+/// drop(root);
+/// ```
+#[track_caller]
+pub fn create_root<R>(f: impl FnOnce() -> R) -> (Root, R) {
+    let root = Root {
+        scope: create_scope(),
+        runtime: Runtime::current(),
+    };
+    let result = root.run(f);
+    (root, result)
+}
+
+//==============================================================================
+// SUBSCRIPTIONS
+//==============================================================================
+
+/// A listener registered with [`Signal::subscribe`].
+///
+/// Dropping it, or calling [`Subscription::unsubscribe`], stops the listener.
+#[must_use = "the listener stops once the subscription is dropped"]
+pub struct Subscription {
+    scope: ScopeId,
+    runtime: Runtime,
+}
+
+impl Subscription {
+    /// Stops the listener, same as dropping the subscription
+    pub fn unsubscribe(self) {}
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.runtime.run(|| dispose_scope(self.scope));
+    }
+}
+
+/// The values of a signal as a [`Stream`], see [`Signal::to_stream`]
+#[must_use = "streams do nothing unless polled"]
+pub struct SignalStream<T> {
+    shared: Arc<spin::Mutex<StreamState<T>>>,
+    _subscription: Subscription,
+}
+
+struct StreamState<T> {
+    /// The value the stream yields next, if it changed since the last one
+    latest: Option<T>,
+    /// Whether the signal was disposed, which ends the stream
+    ended: bool,
+    waker: Option<Waker>,
+}
+
+impl<T> StreamState<T> {
+    /// Wakes the task waiting for the stream, once the lock is released
+    fn wake(shared: &spin::Mutex<Self>, update: impl FnOnce(&mut Self)) {
+        let waker = {
+            let mut state = shared.lock();
+            update(&mut state);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Stream for SignalStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut shared = self.shared.lock();
+        match shared.latest.take() {
+            Some(value) => Poll::Ready(Some(value)),
+            None if shared.ended => Poll::Ready(None),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T: SignalValue + Clone + PartialEq> Signal<T> {
+    /// Calls `f` with the new and the old value whenever the signal changes,
+    /// until the returned [`Subscription`] is dropped.
+    ///
+    /// Unlike effects, subscriptions aren't owned by the current scope, which
+    /// makes them the place for code living outside of components, such as
+    /// analytics, persistence or syncing state with a server. `f` runs when
+    /// changes are flushed, once per batch of changes that leaves the signal
+    /// with another value than before, and doesn't subscribe to the signals
+    /// it reads itself.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use momenta::prelude::*;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let (_root, count) = create_root(|| create_signal(0));
+    /// let changes = Arc::new(Mutex::new(Vec::new()));
+    /// let log = changes.clone();
+    /// let subscription = count.subscribe(move |new, old| log.lock().unwrap().push((*old, *new)));
+    ///
+    /// count.set(1);
+    /// flush();
+    /// subscription.unsubscribe();
+    /// count.set(2);
+    /// flush();
+    /// assert_eq!(*changes.lock().unwrap(), [(0, 1)]);
+    /// ```
+    pub fn subscribe(&self, f: impl FnMut(&T, &T) + Send + 'static) -> Subscription {
+        let (signal, mut f) = (*self, f);
+        let mut previous = None;
+        let scope = create_scope();
+        let function: ScopeFn = Box::new(move || {
+            // reading the signal is what renders the scope again when it changes
+            if let Ok(current) = signal.try_get()
+                && let Some(previous) = previous.replace(current.clone())
+                && current != previous
+            {
+                untrack(|| f(&current, &previous));
+            }
+            Node::Empty
+        });
+        with_scope(scope, |scope| scope.function = Some(function));
+        // the first render only takes note of the current value
+        render_scope(scope);
+        Subscription {
+            scope,
+            runtime: Runtime::current(),
+        }
+    }
+
+    /// The current value of the signal followed by each new one, as a
+    /// [`Stream`] of values.
+    ///
+    /// Values are yielded as they are when the stream is polled, so a stream
+    /// polled less often than the signal changes skips the values in between.
+    /// The stream ends once the signal is disposed.
+    pub fn to_stream(&self) -> SignalStream<T> {
+        let shared = Arc::new(spin::Mutex::new(StreamState {
+            latest: self.try_get().ok(),
+            ended: false,
+            waker: None,
+        }));
+        let state = shared.clone();
+        let subscription = self.subscribe(move |new, _| {
+            StreamState::wake(&state, |state| state.latest = Some(new.clone()));
+        });
+        let state = shared.clone();
+        self.on_dispose(move || StreamState::wake(&state, |state| state.ended = true));
+        SignalStream {
+            shared,
+            _subscription: subscription,
+        }
+    }
+}
+
+impl<T: SignalValue + PartialEq> Signal<T> {
+    /// Create a signal within the current scope holding `init`, and then each
+    /// value `stream` yields, so that async sources such as sockets can feed
+    /// state that components render.
+    ///
+    /// The stream is polled on the browser's event loop, and is dropped once
+    /// it ends or the signal is disposed. Elsewhere, spawn [`Signal::feed`]
+    /// on an executor instead.
+    #[cfg(feature = "wasm")]
+    #[track_caller]
+    pub fn from_stream<S>(stream: S, init: T) -> Signal<T>
+    where
+        S: Stream<Item = T> + 'static,
+    {
+        let signal = create_signal(init);
+        wasm_bindgen_futures::spawn_local(signal.feed(stream));
+        signal
+    }
+
+    /// Sets the signal to each value `stream` yields, as the returned future
+    /// is polled. The future completes once the stream ends or the signal is
+    /// disposed, and runs on the thread that created the signal, so it goes
+    /// to a local executor such as `spawn_local`.
+    pub fn feed<S>(&self, stream: S) -> impl Future<Output = ()> + 'static
+    where
+        S: Stream<Item = T> + 'static,
+    {
+        let (signal, runtime) = (*self, Runtime::current());
+        async move {
+            let mut stream = core::pin::pin!(stream);
+            while let Some(value) = core::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await
+            {
+                if runtime.run(|| batch(|| signal.try_set(value))).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+//==============================================================================
+// HISTORY
+//==============================================================================
+
+/// Options of a [`HistorySignal`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryOptions {
+    /// Most undo steps kept, older ones are dropped
+    pub limit: usize,
+    /// Changes made within this many milliseconds of the last one join its
+    /// undo step, such as the keystrokes of a word. Zero keeps each change as
+    /// a step of its own.
+    ///
+    /// Needs a clock, which there is with the `std` or `wasm` feature.
+    pub coalesce_ms: u64,
+}
+
+impl Default for HistoryOptions {
+    fn default() -> Self {
+        HistoryOptions {
+            limit: 100,
+            coalesce_ms: 0,
+        }
+    }
+}
+
+/// The undo and redo steps of a [`HistorySignal`]
+#[derive(Clone)]
+struct History<T> {
+    options: HistoryOptions,
+    /// Values to go back to, the most recent last
+    past: VecDeque<T>,
+    /// Values undone, the most recently undone last
+    future: Vec<T>,
+    /// When the last step was changed, for coalescing
+    last_change: Option<u64>,
+    /// Depth of the checkpoints running, and whether the outermost one
+    /// recorded its step yet
+    checkpoints: usize,
+    checkpoint_recorded: bool,
+}
+
+impl<T> History<T> {
+    /// Records a value to go back to, dropping the oldest one past the limit
+    fn push_past(&mut self, value: T) {
+        self.past.push_back(value);
+        if self.past.len() > self.options.limit {
+            self.past.pop_front();
+        }
+    }
+}
+
+/// A signal that keeps the values it had so they can be undone and redone,
+/// see [`create_history_signal`]
+pub struct HistorySignal<T> {
+    value: Signal<T>,
+    history: Signal<History<T>>,
+    can_undo: Signal<bool>,
+    can_redo: Signal<bool>,
+}
+
+impl<T> Clone for HistorySignal<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for HistorySignal<T> {}
+
+/// Create a signal within the current scope which can undo and redo its
+/// changes.
+///
+/// Each change of the value, as far as `PartialEq` can tell, is an undo step,
+/// unless it joins the previous one: changes made within
+/// [`HistoryOptions::coalesce_ms`] of each other, or within one
+/// [`HistorySignal::checkpoint`], are undone together.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// let (_root, text) = create_root(|| create_history_signal(String::new(), HistoryOptions::default()));
+/// text.set("Hello".to_string());
+/// text.checkpoint(|| {
+///     text.update(|text| text.push_str(", "));
+///     text.update(|text| text.push_str("World"));
+/// });
+///
+/// text.undo();
+/// assert_eq!(text.get(), "Hello");
+/// text.redo();
+/// assert_eq!(text.get(), "Hello, World");
+/// assert!(text.can_undo().get() && !text.can_redo().get());
+/// ```
+#[track_caller]
+pub fn create_history_signal<T>(init: T, options: HistoryOptions) -> HistorySignal<T>
+where
+    T: SignalValue + PartialEq + Clone,
+{
+    let value = create_signal(init);
+    let history = scope_signal(|| History {
+        options,
+        past: VecDeque::new(),
+        future: Vec::new(),
+        last_change: None,
+        checkpoints: 0,
+        checkpoint_recorded: false,
+    })
+    .unwrap(); // safe, `create_signal` checked the scope
+    HistorySignal {
+        value,
+        history,
+        can_undo: create_signal(false),
+        can_redo: create_signal(false),
+    }
+}
+
+impl<T: SignalValue + PartialEq + Clone> HistorySignal<T> {
+    /// Access the current value immutably
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.value.with(f)
+    }
+
+    /// Get the cloned current value
+    pub fn get(&self) -> T {
+        self.value.get()
+    }
+
+    /// The current value as a signal that can only be read
+    pub fn read(&self) -> ReadSignal<T> {
+        self.value.into()
+    }
+
+    /// Whether there is a change to undo, which components can read to
+    /// enable an undo button
+    pub fn can_undo(&self) -> ReadSignal<bool> {
+        self.can_undo.into()
+    }
+
+    /// Whether there is an undone change to redo
+    pub fn can_redo(&self) -> ReadSignal<bool> {
+        self.can_redo.into()
+    }
+
+    /// Update the value, recording an undo step if it changed
+    pub fn set(&self, value: T) {
+        let previous = self.value.get_untracked();
+        if previous == value {
+            return;
+        }
+        let now = now_ms();
+        self.history.update(|history| {
+            let coalesced = match (history.last_change, now) {
+                (Some(last), Some(now)) => now.saturating_sub(last) < history.options.coalesce_ms,
+                _ => false,
+            };
+            let grouped = history.checkpoints > 0 && history.checkpoint_recorded;
+            if !coalesced && !grouped {
+                history.push_past(previous);
+            }
+            history.checkpoint_recorded |= history.checkpoints > 0;
+            history.last_change = now;
+            history.future.clear();
+        });
+        batch(|| {
+            self.value.set(value);
+            self.sync();
+        });
+    }
+
+    /// Update the value from a copy of it, recording an undo step if it changed
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        let mut value = self.value.get_untracked();
+        f(&mut value);
+        self.set(value);
+    }
+
+    /// Runs `f` as a single undo step, however many changes it makes
+    pub fn checkpoint<R>(&self, f: impl FnOnce() -> R) -> R {
+        /// Ends the checkpoint, even if `f` panics
+        struct CheckpointGuard<T: Clone + 'static>(Signal<History<T>>);
+
+        impl<T: Clone + 'static> Drop for CheckpointGuard<T> {
+            fn drop(&mut self) {
+                // a disposed history has no checkpoint left to end
+                if self.0.try_with(|_| ()).is_err() {
+                    return;
+                }
+                self.0.update(|history| {
+                    history.checkpoints -= 1;
+                    // the next change starts a step of its own
+                    history.last_change = None;
+                });
+            }
+        }
+
+        self.history.update(|history| {
+            if history.checkpoints == 0 {
+                history.checkpoint_recorded = false;
+            }
+            history.checkpoints += 1;
+        });
+        let _guard = CheckpointGuard(self.history);
+        batch(f)
+    }
+
+    /// Goes back to the value before the last undo step, returning whether
+    /// there was one
+    pub fn undo(&self) -> bool {
+        let current = self.value.get_untracked();
+        let previous = self.history.with_mut(|history| {
+            let previous = history.past.pop_back()?;
+            history.future.push(current);
+            history.last_change = None;
+            Some(previous)
+        });
+        self.restore(previous)
+    }
+
+    /// Goes forward to the value before the last undo, returning whether
+    /// there was one
+    pub fn redo(&self) -> bool {
+        let current = self.value.get_untracked();
+        let next = self.history.with_mut(|history| {
+            let next = history.future.pop()?;
+            history.push_past(current);
+            history.last_change = None;
+            Some(next)
+        });
+        self.restore(next)
+    }
+
+    /// Drops every undo and redo step, keeping the current value
+    pub fn clear_history(&self) {
+        self.history.update(|history| {
+            history.past.clear();
+            history.future.clear();
+            history.last_change = None;
+        });
+        self.sync();
+    }
+
+    fn restore(&self, value: Option<T>) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+        batch(|| {
+            self.value.set(value);
+            self.sync();
+        });
+        true
+    }
+
+    /// Brings `can_undo` and `can_redo` up to date with the steps kept
+    fn sync(&self) {
+        let (can_undo, can_redo) = self
+            .history
+            .with_untracked(|history| (!history.past.is_empty(), !history.future.is_empty()))
+            .unwrap_or_default();
+        self.can_undo.set(can_undo);
+        self.can_redo.set(can_redo);
+    }
+}
+
+//==============================================================================
+// RECORDING
+//==============================================================================
+
+/// Where a scope is in the tree of scopes, as the position of each scope among
+/// the children of its parent, from the root down.
+///
+/// Unlike a [`ScopeId`], which depends on every scope created before, a path
+/// only depends on how the app rendered, so the same app rendered the same
+/// way has its scopes at the same paths again, in another session as well.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScopePath(Vec<usize>);
+
+impl core::fmt::Display for ScopePath {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "/");
+        }
+        self.0
+            .iter()
+            .try_for_each(|position| write!(f, "/{position}"))
+    }
+}
+
+impl ScopePath {
+    fn parse(text: &str) -> Option<ScopePath> {
+        let positions = text.strip_prefix('/')?;
+        if positions.is_empty() {
+            return Some(ScopePath::default());
+        }
+        let positions = positions.split('/').map(|position| position.parse().ok());
+        positions.collect::<Option<_>>().map(ScopePath)
+    }
+}
+
+/// Identifies a signal by the scope that created it and its position among
+/// the signals that scope created, so that rendering the same app in a fresh
+/// runtime gives its signals the same ids again.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SignalId {
+    scope: ScopePath,
+    position: usize,
+}
+
+impl core::fmt::Display for SignalId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}#{}", self.scope, self.position)
+    }
+}
+
+impl SignalId {
+    fn parse(text: &str) -> Option<SignalId> {
+        let (scope, position) = text.rsplit_once('#')?;
+        Some(SignalId {
+            scope: ScopePath::parse(scope)?,
+            position: position.parse().ok()?,
+        })
+    }
+}
+
+impl<T> Signal<T> {
+    /// The id of the signal, as found in a [`Recording`]
+    pub fn id(&self) -> SignalId {
+        let rt = self.runtime().unwrap_or_else(|error| self.fail(error));
+        signal_id(&rt, self.id).unwrap_or_else(|| self.fail(SignalError::Disposed))
+    }
+}
+
+/// The path of a live scope of the current runtime
+fn scope_path(scope_id: ScopeId) -> Option<ScopePath> {
+    let rt = runtime();
+    let scopes = rt.scopes.borrow();
+    let (mut path, mut current) = (Vec::new(), scope_id);
+    while let Some(parent) = scopes.get(current.0)?.parent {
+        let children = &scopes.get(parent.0)?.children;
+        path.push(children.iter().position(|child| *child == current)?);
+        current = parent;
+    }
+    path.reverse();
+    Some(ScopePath(path))
+}
+
+/// The live scope at `path` below `root`
+fn scope_at(root: ScopeId, path: &ScopePath) -> Option<ScopeId> {
+    let rt = runtime();
+    let scopes = rt.scopes.borrow();
+    path.0.iter().try_fold(root, |scope, position| {
+        scopes.get(scope.0)?.children.get(*position).copied()
+    })
+}
+
+/// The id of a live signal
+fn signal_id(rt: &RuntimeState, id: Key) -> Option<SignalId> {
+    let owner = rt.signals.borrow().get(id)?.owner;
+    let position = rt
+        .scopes
+        .borrow()
+        .get(owner.0)?
+        .signals
+        .iter()
+        .position(|signal| *signal == id)?;
+    Some(SignalId {
+        scope: scope_path(owner)?,
+        position,
+    })
+}
+
+/// Values that can be written to a [`Recording`] and read back when it is
+/// replayed
+pub trait Replayable: Sized + 'static {
+    fn encode(&self) -> String;
+    fn decode(text: &str) -> Option<Self>;
+}
+
+macro_rules! replayable_from_str {
+    ($($ty:ty),*) => {
+        $(
+            impl Replayable for $ty {
+                fn encode(&self) -> String {
+                    self.to_string()
+                }
+
+                fn decode(text: &str) -> Option<Self> {
+                    text.parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+replayable_from_str!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, String
+);
+
+impl Replayable for &'static str {
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    // strings are interned, so each distinct one is leaked once however many
+    // times it's replayed
+    fn decode(text: &str) -> Option<Self> {
+        static INTERNED: spin::Mutex<BTreeSet<&'static str>> = spin::Mutex::new(BTreeSet::new());
+        let mut interned = INTERNED.lock();
+        if let Some(text) = interned.get(text) {
+            return Some(*text);
+        }
+        let text: &'static str = Box::leak(text.into());
+        interned.insert(text);
+        Some(text)
+    }
+}
+
+impl<T: Replayable> Replayable for Option<T> {
+    fn encode(&self) -> String {
+        match self {
+            Some(value) => alloc::format!("={}", value.encode()),
+            None => String::new(),
+        }
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        match text.strip_prefix('=') {
+            Some(value) => T::decode(value).map(Some),
+            None => text.is_empty().then_some(None),
+        }
+    }
+}
+
+/// Encodes and decodes the values of one type behind `dyn Any`
+#[derive(Clone, Copy)]
+struct Codec {
+    encode: fn(&dyn Any) -> Option<String>,
+    decode: fn(&str) -> Option<Rc<dyn Any>>,
+}
+
+impl Codec {
+    fn of<T: Replayable>() -> (TypeId, Codec) {
+        let codec = Codec {
+            encode: |value| value.downcast_ref::<T>().map(T::encode),
+            decode: |text| T::decode(text).map(|value| Rc::new(value) as Rc<dyn Any>),
+        };
+        (TypeId::of::<T>(), codec)
+    }
+}
+
+static CODECS: spin::Once<spin::Mutex<BTreeMap<TypeId, Codec>>> = spin::Once::new();
+
+/// The registered codecs, starting with the ones of the built-in types
+fn codecs() -> &'static spin::Mutex<BTreeMap<TypeId, Codec>> {
+    CODECS.call_once(|| {
+        macro_rules! built_in {
+            ($($ty:ty),*) => { [$(Codec::of::<$ty>()),*] };
+        }
+        let built_in = built_in!(
+            bool,
+            char,
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            isize,
+            u8,
+            u16,
+            u32,
+            u64,
+            u128,
+            usize,
+            f32,
+            f64,
+            String,
+            &'static str
+        );
+        spin::Mutex::new(built_in.into_iter().collect())
+    })
+}
+
+/// Lets signals holding `T` be recorded and replayed. The primitive types,
+/// `String` and `&'static str` are registered already.
+pub fn register_replayable<T: Replayable>() {
+    let (type_id, codec) = Codec::of::<T>();
+    codecs().lock().insert(type_id, codec);
+}
+
+/// The codec for values of `type_id`, if there is one. It's copied out of
+/// the lock, as encoding and decoding run user code.
+fn codec(type_id: TypeId) -> Option<Codec> {
+    codecs().lock().get(&type_id).copied()
+}
+
+/// Something that happened while a runtime was recording
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedEntry {
+    /// A signal changed, through `set` or in place. `value` is the new value,
+    /// or `None` if its type isn't [registered](register_replayable).
+    /// `scope` is the scope that was running, if any.
+    Set {
+        scope: Option<ScopePath>,
+        signal: SignalId,
+        value: Option<String>,
+        at_ms: Option<u64>,
+    },
+    /// An event handler was dispatched an event of type `name`. `scope` is
+    /// the scope that rendered the handler.
+    Event {
+        scope: Option<ScopePath>,
+        name: String,
+        at_ms: Option<u64>,
+    },
+}
+
+/// What a runtime recorded between [`Runtime::start_recording`] and
+/// [`Runtime::stop_recording`].
+///
+/// A recording is portable: its `Display` output, one entry per line, is read
+/// back with [`Recording::parse`], such as from a bug report.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub entries: Vec<RecordedEntry>,
+}
+
+impl core::fmt::Display for Recording {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let scope = |scope: &Option<ScopePath>| {
+            scope.as_ref().map_or("-".into(), |scope| scope.to_string())
+        };
+        let at = |at: &Option<u64>| at.map_or("-".into(), |at| at.to_string());
+        for entry in &self.entries {
+            match entry {
+                RecordedEntry::Set {
+                    scope: set_in,
+                    signal,
+                    value,
+                    at_ms,
+                } => {
+                    let value = value
+                        .as_deref()
+                        .map_or("-".into(), |value| alloc::format!("={}", escape(value)));
+                    writeln!(f, "set {} {signal} {} {value}", scope(set_in), at(at_ms))?
+                }
+                RecordedEntry::Event {
+                    scope: set_in,
+                    name,
+                    at_ms,
+                } => writeln!(f, "event {} {} {}", scope(set_in), at(at_ms), escape(name))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Recording {
+    /// Reads a recording back from its `Display` output
+    pub fn parse(text: &str) -> Option<Recording> {
+        fn optional<T>(text: &str, f: impl FnOnce(&str) -> Option<T>) -> Option<Option<T>> {
+            match text {
+                "-" => Some(None),
+                text => f(text).map(Some),
+            }
+        }
+
+        let entries = text.lines().filter(|line| !line.is_empty()).map(|line| {
+            let mut parts = line.splitn(5, ' ');
+            match parts.next()? {
+                "set" => Some(RecordedEntry::Set {
+                    scope: optional(parts.next()?, ScopePath::parse)?,
+                    signal: SignalId::parse(parts.next()?)?,
+                    at_ms: optional(parts.next()?, |text| text.parse().ok())?,
+                    value: optional(parts.next()?, |text| {
+                        text.strip_prefix('=').and_then(unescape)
+                    })?,
+                }),
+                "event" => {
+                    let mut parts = line["event ".len()..].splitn(3, ' ');
+                    Some(RecordedEntry::Event {
+                        scope: optional(parts.next()?, ScopePath::parse)?,
+                        at_ms: optional(parts.next()?, |text| text.parse().ok())?,
+                        name: unescape(parts.next()?)?,
+                    })
+                }
+                _ => None,
+            }
+        });
+        Some(Recording {
+            entries: entries.collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// Keeps a recorded value on a line of its own
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => unescaped.push('\n'),
+                '\\' => unescaped.push('\\'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+    Some(unescaped)
+}
+
+impl Runtime {
+    /// Starts recording every signal change and dispatched event, dropping
+    /// whatever was recorded so far
+    pub fn start_recording(&self) {
+        self.state.recording.replace(Some(Vec::new()));
+    }
+
+    /// Stops recording and returns what was recorded since
+    /// [`Runtime::start_recording`]
+    pub fn stop_recording(&self) -> Recording {
+        Recording {
+            entries: self.state.recording.take().unwrap_or_default(),
+        }
+    }
+}
+
+/// Records that signal `id` changed to `value`, if the runtime is recording
+fn record_set(rt: &RuntimeState, id: Key, value: &dyn Any) {
+    if rt.recording.borrow().is_none() {
+        return;
+    }
+    let Some(signal) = signal_id(rt, id) else {
+        return;
+    };
+    let value = codec(value.type_id()).and_then(|codec| (codec.encode)(value));
+    let entry = RecordedEntry::Set {
+        scope: rt.current_scope.get().and_then(scope_path),
+        signal,
+        value,
+        at_ms: now_ms(),
+    };
+    if let Some(entries) = rt.recording.borrow_mut().as_mut() {
+        entries.push(entry);
+    }
+}
+
+/// Records that an event handler rendered by `scope` was dispatched an event
+/// of type `name`, if the runtime is recording
+pub(crate) fn record_event(name: &str, scope: Option<ScopeId>) {
+    let rt = runtime();
+    if rt.recording.borrow().is_none() {
+        return;
+    }
+    let entry = RecordedEntry::Event {
+        scope: scope.and_then(scope_path),
+        name: name.into(),
+        at_ms: now_ms(),
+    };
+    if let Some(entries) = rt.recording.borrow_mut().as_mut() {
+        entries.push(entry);
+    }
+}
+
+/// Wraps `handler` so that each call is recorded as an event of type `name`,
+/// dispatched to the scope creating the handler, while its runtime records.
+///
+/// Handlers attached to the DOM are recorded already, so this is for events
+/// coming from elsewhere, such as a native host driving the app.
+pub fn recorded_handler<E>(name: &str, mut handler: impl FnMut(E)) -> impl FnMut(E) {
+    let (name, scope) = (String::from(name), get_current_scope());
+    move |event| {
+        record_event(&name, scope);
+        handler(event)
+    }
+}
+
+/// Why a recording couldn't be replayed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The app didn't create the signal, so it isn't the app that was recorded
+    MissingSignal(SignalId),
+    /// The value of the signal wasn't recorded, as its type isn't registered
+    /// with [`register_replayable`]
+    MissingValue(SignalId),
+    /// The recorded value doesn't decode to the type the signal holds
+    InvalidValue(SignalId),
+}
+
+impl core::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ReplayError::MissingSignal(id) => write!(f, "the app has no signal {id}"),
+            ReplayError::MissingValue(id) => write!(
+                f,
+                "the value of signal {id} wasn't recorded, as its type isn't replayable"
+            ),
+            ReplayError::InvalidValue(id) => write!(
+                f,
+                "the recorded value of signal {id} doesn't fit the value it holds"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ReplayError {}
+
+/// Renders `app` in a fresh runtime, then applies the signal changes of
+/// `recording` one entry at a time, calling `check` with each entry and the
+/// rendered node once its changes are flushed.
+///
+/// Signals changed by the app itself as it renders change again on their
+/// own, so replays follow the same path as the recorded session. Events are
+/// replayed through the changes their handlers made, which makes them steps
+/// to check the output at.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+/// use momenta::signals::{RecordedEntry, Recording, recorded_handler, replay};
+/// use std::sync::Mutex;
+///
+/// // the click handler of the counter, for the session below to call
+/// static CLICK: Mutex<Option<Box<dyn FnMut(()) + Send>>> = Mutex::new(None);
+///
+/// #[component]
+/// fn Counter() -> Node {
+///     let count = create_signal(0);
+///     let click = recorded_handler("click", move |_| count.update(|count| *count += 1));
+///     *CLICK.lock().unwrap() = Some(Box::new(click));
+///     rsx!(<p>{count}</p>)
+/// }
+///
+/// let app = || rsx!(<main><Counter /></main>);
+///
+/// // a session rendering the app and clicking the counter twice
+/// let runtime = Runtime::new();
+/// let _root = runtime.run(|| create_root(app));
+/// runtime.start_recording();
+/// for _ in 0..2 {
+///     let mut click = CLICK.lock().unwrap().take().unwrap();
+///     runtime.run(|| {
+///         click(());
+///         flush();
+///     });
+/// }
+/// let log = runtime.stop_recording().to_string();
+///
+/// // the app is rendered again in a fresh runtime, where the counter and its
+/// // signal are found at the same place in the tree of scopes
+/// let mut rendered = Vec::new();
+/// let recording = Recording::parse(&log).unwrap();
+/// replay(&recording, app, |entry, node| {
+///     if let RecordedEntry::Event { scope, name, .. } = entry {
+///         assert_eq!(name, "click");
+///         assert_eq!(scope.as_ref().unwrap().to_string(), "/0");
+///     }
+///     rendered.push(node.to_string());
+/// })
+/// .unwrap();
+/// assert_eq!(
+///     rendered,
+///     ["<main><p>0</p></main>", "<main><p>1</p></main>", "<main><p>1</p></main>", "<main><p>2</p></main>"]
+/// );
+/// ```
+pub fn replay(
+    recording: &Recording,
+    app: impl FnMut() -> Node + Send + 'static,
+    mut check: impl FnMut(&RecordedEntry, &Node),
+) -> Result<(), ReplayError> {
+    Runtime::new().run(|| {
+        let root = match run_scope(app, |_| {}) {
+            Node::Component(component) => component.scope(),
+            _ => unreachable!("scopes render as components"),
+        };
+        flush();
+        let result = recording.entries.iter().try_for_each(|entry| {
+            if let RecordedEntry::Set { signal, value, .. } = entry {
+                let value = value
+                    .as_deref()
+                    .ok_or_else(|| ReplayError::MissingValue(signal.clone()))?;
+                replay_set(root, signal, value)?;
+            }
+            flush();
+            let node = latest_output(root).unwrap_or(Node::Empty);
+            check(entry, &node);
+            Ok(())
+        });
+        dispose_scope(root);
+        result
+    })
+}
+
+/// Sets signal `id` of the app rendered in `root` to the recorded `value`
+fn replay_set(root: ScopeId, id: &SignalId, value: &str) -> Result<(), ReplayError> {
+    let rt = runtime();
+    let missing = || ReplayError::MissingSignal(id.clone());
+    let key = scope_at(root, &id.scope)
+        .and_then(|scope| with_scope(scope, |scope| scope.signals.get(id.position).copied()))
+        .flatten()
+        .ok_or_else(missing)?;
+    let type_id = {
+        let signals = rt.signals.borrow();
+        let signal = signals.get(key).ok_or_else(missing)?;
+        (*signal.value).type_id()
+    };
+    let decoded = codec(type_id)
+        .and_then(|codec| (codec.decode)(value))
+        .ok_or_else(|| ReplayError::InvalidValue(id.clone()))?;
+    let (previous, subscribers) = {
+        let mut signals = rt.signals.borrow_mut();
+        let signal = signals.get_mut(key).ok_or_else(missing)?;
+        let previous = core::mem::replace(&mut signal.value, decoded);
+        (previous, signal.subscribers.clone())
+    };
+    // the old value may run user code as it's dropped
+    drop(previous);
+    subscribers.into_iter().for_each(schedule);
+    Ok(())
+}
+
+//==============================================================================
+// CONTEXT
+//==============================================================================
+
+/// Makes a value available to every scope below the current one, through
+/// [`use_context`].
+///
+/// A scope provides one value per type, and a descendant gets the value of the
+/// nearest scope providing one. The value is provided again on every render, so
+/// a render that doesn't call this stops providing it. Provide a signal to have
+/// descendants render again when the value changes.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+///
+/// #[derive(Clone)]
+/// struct Theme(&'static str);
+///
+/// #[component]
+/// fn Button() -> Node {
+///     let theme = use_context::<Theme>().map_or("light", |theme| theme.0);
+///     rsx!(<button class={theme}>Save</button>)
+/// }
+///
+/// #[component]
+/// fn App() -> Node {
+///     provide_context(Theme("dark"));
+///     rsx!(<Button />)
+/// }
+///
+/// assert_eq!(rsx!(<App />).to_string(), r#"<button class="dark">Save</button>"#);
+/// ```
+pub fn provide_context<T: Clone + 'static>(value: T) {
+    let scope_id = get_current_scope()
+        .ok_or(SignalCreationError::OutsideScope)
+        .unwrap(); // safe, we want to panic if not in scope
+    let replaced = with_scope(scope_id, |scope| {
+        scope.contexts.insert(TypeId::of::<T>(), Rc::new(value))
+    });
+    // a value provided earlier in the render is dropped outside of the borrow
+    drop(replaced);
+}
+
+/// Gets the value of type `T` provided by the nearest scope above the current
+/// one, or by the current scope itself, see [`provide_context`]
+pub fn use_context<T: Clone + 'static>() -> Option<T> {
+    let mut next = get_current_scope();
+    while let Some(scope_id) = next {
+        let (value, parent) = with_scope(scope_id, |scope| {
+            (
+                scope.contexts.get(&TypeId::of::<T>()).cloned(),
+                scope.parent,
+            )
+        })?;
+        // cloned outside of the borrow, as `Clone` is user code
+        if let Some(value) = value {
+            return value.downcast_ref::<T>().cloned();
+        }
+        next = parent;
+    }
+    None
+}
+
+/// Queues `run` until the busy handler identified by `handler` returns
+#[cfg_attr(not(any(feature = "wasm", test)), allow(dead_code))]
+pub(crate) fn defer_event(handler: usize, run: Box<dyn FnOnce()>) {
+    runtime()
+        .deferred_events
+        .borrow_mut()
+        .push_back((handler, run));
+}
+
+/// Takes the oldest event queued for `handler`, see [`defer_event`]
+#[cfg_attr(not(any(feature = "wasm", test)), allow(dead_code))]
+pub(crate) fn take_deferred_event(handler: usize) -> Option<Box<dyn FnOnce()>> {
+    let rt = runtime();
+    let mut deferred = rt.deferred_events.borrow_mut();
+    let position = deferred.iter().position(|(id, _)| *id == handler)?;
+    deferred.remove(position).map(|(_, run)| run)
+}
+
+//==============================================================================
+// SCOPE MANAGEMENT
+//==============================================================================
+
+/// Run function within new reactive scope
+pub(crate) fn run_scope(
+    scope_fn: impl FnMut() -> Node + Send + 'static,
+    callback: impl Fn(&Node) + Send + Sync + 'static,
+) -> Node {
+    let scope_id = create_scope();
+    own_scope(scope_id);
+    mount_scope(scope_id, scope_fn, callback)
+}
+
+/// Run function within a child scope of the current scope.
+///
+/// Children are matched to the ones the current scope rendered last time by
+/// kind and key, or by their position among the children of the same kind
+/// when they have no key. A matched child keeps its scope along with its
+/// signals, and only renders again if `changed` tells its props apart from
+/// the ones it last rendered with.
+pub(crate) fn run_child_scope<P: Send + Sync + 'static>(
+    key: Option<String>,
+    kind: TypeId,
+    props: P,
+    changed: impl FnOnce(&P, &P) -> bool,
+    render: impl Fn(&P) -> Node + Send + 'static,
+    callback: impl Fn(&Node) + Send + Sync + 'static,
+) -> Node {
+    let parent = get_current_scope();
+    let matched = parent.and_then(|parent| {
+        with_scope(parent, |scope| {
+            scope.matched_children.take(kind, key.clone())
+        })
+    });
+    let reused = matched.as_ref().and_then(|(_, reused)| *reused);
+    let scope_id = reused.unwrap_or_else(create_scope);
+    own_scope(scope_id);
+
+    if let (Some(parent), Some((id, _))) = (parent, matched) {
+        with_scope(parent, |scope| {
+            scope.matched_children.current.insert(id, scope_id)
+        });
+    }
+    let key = key.unwrap_or_default();
+    with_scope(scope_id, |scope| scope.key = key.clone());
+
+    if reused.is_some() {
+        let previous = with_scope(scope_id, |scope| scope.props.clone()).flatten();
+        // compared outside of the borrow, as `PartialEq` is user code
+        let unchanged = previous
+            .as_ref()
+            .and_then(|previous| previous.downcast_ref::<P>())
+            .is_some_and(|previous| !changed(previous, &props));
+        if unchanged && let Some(node) = latest_output(scope_id) {
+            return Node::Component(ComponentNode {
+                scope: scope_id,
+                key,
+                node: Box::new(node),
+            });
+        }
+    }
+
+    let props = Arc::new(props);
+    let replaced = with_scope(scope_id, |scope| scope.props.replace(props.clone()));
+    drop(replaced);
+    mount_scope(scope_id, move || render(&props), callback)
+}
+
+fn mount_scope(
+    scope_id: ScopeId,
+    scope_fn: impl FnMut() -> Node + Send + 'static,
+    callback: impl Fn(&Node) + Send + Sync + 'static,
+) -> Node {
+    let replaced = with_scope(scope_id, |scope| {
+        (
+            scope.function.replace(Box::new(scope_fn)),
+            scope.callback.replace(Rc::new(callback)),
+        )
+    });
+    drop(replaced);
+
+    render_scope(scope_id)
+}
+
+//==============================================================================
+// INTERNAL FUNCTIONS
+//==============================================================================
+
+fn create_scope() -> ScopeId {
+    ScopeId(runtime().scopes.borrow_mut().insert(ScopeState::default()))
+}
+
+/// Makes `scope_id` a child of the current scope, which disposes it once a
+/// render of its own no longer creates it
+fn own_scope(scope_id: ScopeId) {
+    let Some(parent) = get_current_scope() else {
+        return;
+    };
+    let depth = with_scope(parent, |scope| {
+        scope.children.push(scope_id);
+        scope.depth + 1
+    });
+    with_scope(scope_id, |scope| {
+        scope.depth = depth.unwrap_or_default();
+        scope.parent = Some(parent);
+    });
+}
+
+/// Runs `f` on the state of a live scope.
+///
+/// `f` runs while the scopes are borrowed, so it must not call into user code.
+fn with_scope<R>(scope_id: ScopeId, f: impl FnOnce(&mut ScopeState) -> R) -> Option<R> {
+    runtime().scopes.borrow_mut().get_mut(scope_id.0).map(f)
+}
+
+/// Frees a scope along with its child scopes, signals and effects, running its
+/// cleanup hooks.
+///
+/// Handles to the freed signals are stale from then on, and using one panics.
+fn dispose_scope(scope_id: ScopeId) {
+    let rt = runtime();
+    let Some(scope) = rt.scopes.borrow_mut().remove(scope_id.0) else {
+        return;
+    };
+    rt.pending_scope_renders
+        .borrow_mut()
+        .remove(&(scope.depth, scope_id));
+
+    // children go first, so cleanups run from the leaves up while the signals
+    // they may read are still around
+    scope.children.iter().copied().for_each(dispose_scope);
+    scope.cleanups.into_iter().for_each(|cleanup| cleanup());
+
+    // values are dropped outside of the borrow, as their `Drop` is user code
+    let values: Vec<_> = {
+        let mut signals = rt.signals.borrow_mut();
+        for id in &scope.dependencies {
+            if let Some(signal) = signals.get_mut(*id) {
+                signal.subscribers.remove(&scope_id);
+            }
+        }
+        scope
+            .signals
+            .iter()
+            .filter_map(|id| signals.remove(*id))
+            .collect()
+    };
+    for signal in values {
+        signal.on_dispose.into_iter().for_each(|f| f());
+    }
+}
+
+pub(crate) fn get_current_scope() -> Option<ScopeId> {
+    runtime().current_scope.get()
+}
+
+fn set_current_scope(scope_id: Option<ScopeId>) {
+    runtime().current_scope.set(scope_id);
+}
+
+struct ScopeGuard {
+    previous_scope: Option<ScopeId>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        set_current_scope(self.previous_scope);
+    }
+}
+
+/// Records the hook the caller creates, and panics if the scope created a
+/// different one in its place last render.
+///
+/// Hooks are told apart by the order they're created in, so one created
+/// conditionally or in a loop would take over the state of another.
+#[cfg(debug_assertions)]
+#[track_caller]
+fn check_hook(scope_id: ScopeId, kind: &'static str, type_name: &'static str) {
+    let hook = Hook {
+        kind,
+        type_name,
+        location: core::panic::Location::caller(),
+    };
+    let previous = with_scope(scope_id, |scope| {
+        let position = scope.hook_cursor;
+        scope.hook_cursor += 1;
+        match scope.hooks.get(position) {
+            Some(previous) => Some((position, *previous)),
+            None => {
+                scope.hooks.push(hook);
+                None
+            }
+        }
+    })
+    .flatten();
+
+    if let Some((position, previous)) = previous
+        && previous != hook
+    {
+        panic!(
+            "hook order changed in scope {scope_id:?}: hook #{position} was the {previous} \
+             on the last render, but is now the {hook}. Signals, effects and memos must be \
+             created in the same order on every render, never conditionally or in a loop \
+             of varying length"
+        );
+    }
+}
+
+struct UntrackGuard {
+    previous: bool,
+}
+
+impl Drop for UntrackGuard {
+    fn drop(&mut self) {
+        runtime().untracked.set(self.previous);
+    }
+}
+
+fn render_scope(scope_id: ScopeId) -> Node {
+    let rt = runtime();
+    let _guard = ScopeGuard {
+        previous_scope: get_current_scope(),
+    };
+    set_current_scope(Some(scope_id));
+    // the scope tracks its reads even when rendered from an untracked read
+    let _untracked = UntrackGuard {
+        previous: rt.untracked.replace(false),
+    };
+
+    // rendering now covers any render it was waiting for
+    unschedule(scope_id);
+    let was_rendering = rt.rendering_scope.replace(Some(scope_id));
+    // changes made so far by the render this one is nested in are kept for it
+    let outer_changes = core::mem::take(&mut *rt.scope_signal_changes.borrow_mut());
+
+    let prepared = with_scope(scope_id, |scope| {
+        scope.stale = false;
+        // dependencies are collected again, also when rendering within the
+        // render of another scope, such as a memo read by it
+        let dependencies = core::mem::take(&mut scope.dependencies);
+        let function = scope.function.take();
+        if function.is_none() {
+            return (None, dependencies, Vec::new(), Vec::new(), BTreeMap::new());
+        }
+        // children from the last render become candidates for reuse
+        scope.matched_children.start();
+        let children = core::mem::take(&mut scope.children);
+        let cleanups = core::mem::take(&mut scope.cleanups);
+        // the render provides its contexts again
+        let contexts = core::mem::take(&mut scope.contexts);
+        (function, dependencies, children, cleanups, contexts)
+    });
+    // a scope disposed while waiting to render has nothing left to do
+    let Some((scope_fn, dependencies, mut previous_children, cleanups, contexts)) = prepared else {
+        rt.rendering_scope.set(was_rendering);
+        *rt.scope_signal_changes.borrow_mut() = outer_changes;
+        return Node::Empty;
+    };
+    drop(contexts);
+    cleanups.into_iter().for_each(|cleanup| cleanup());
+
+    {
+        let mut signals = rt.signals.borrow_mut();
+        for id in dependencies {
+            if let Some(signal) = signals.get_mut(id) {
+                signal.subscribers.remove(&scope_id);
+            }
+        }
+    }
+
+    let node = scope_fn.map(|mut fnc| {
+        let node = fnc();
+        let finished = with_scope(scope_id, |scope| {
+            // keep a function mounted while this one ran, if any
+            scope.function.get_or_insert(fnc);
+            scope.matched_children.previous.clear();
+            // children that weren't rendered again are gone for good
+            let current: BTreeSet<_> = scope.children.iter().collect();
+            previous_children.retain(|child| !current.contains(child));
+            (scope.key.clone(), scope.output.replace(node.clone()))
+        });
+        // the previous output goes once the borrow is released
+        let (key, _previous_output) = finished.unwrap_or_default();
+        previous_children.iter().copied().for_each(dispose_scope);
+        Node::Component(ComponentNode {
+            scope: scope_id,
+            key,
+            node: Box::new(node),
+        })
+    });
+
+    if let Some(ref node) = node {
+        let callback = with_scope(scope_id, |scope| scope.callback.clone()).flatten();
+        if let Some(callback) = callback {
+            callback(node);
+        }
+    }
+
+    with_scope(scope_id, |scope| {
+        scope.signal_cursor = 0;
+        #[cfg(debug_assertions)]
+        {
+            scope.hook_cursor = 0;
+        }
+    });
+    run_scope_effects(scope_id);
+
+    let signal_changes = {
+        let mut changes = rt.scope_signal_changes.borrow_mut();
+        rt.rendering_scope.set(was_rendering);
+        let changes = core::mem::replace(&mut *changes, outer_changes);
+        (!changes.is_empty()).then_some(changes)
+    };
+
+    if let Some(changes) = signal_changes {
+        let subscribers: Vec<_> = {
+            let signals = rt.signals.borrow();
+            changes
+                .iter()
+                .filter_map(|id| signals.get(*id))
+                .flat_map(|signal| signal.subscribers.iter().copied())
+                .collect()
+        };
+        subscribers.into_iter().for_each(schedule);
+
+        if was_rendering.is_none() {
+            request_flush();
+        }
+    }
+
+    node.unwrap_or(Node::Empty)
+}
+
+/// The output of a scope's last render, brought up to date with the renders
+/// its descendants did since
+fn latest_output(scope_id: ScopeId) -> Option<Node> {
+    // a scope still waiting to render would hand back an outdated output
+    if unschedule(scope_id) {
+        render_scope(scope_id);
+    }
+    let mut output = with_scope(scope_id, |scope| scope.output.clone()).flatten()?;
+    output.refresh(&latest_output);
+    Some(output)
+}
+
+/// Runs the effects the scope created for the first time
+fn run_scope_effects(scope_id: ScopeId) {
+    let effects = with_scope(scope_id, |scope| core::mem::take(&mut scope.new_effects));
+    effects.unwrap_or_default().into_iter().for_each(|effect| {
+        render_scope(effect);
+    });
+}
+
+/// Queues a scope to render with the next flush
+fn schedule(scope_id: ScopeId) {
+    if let Some(depth) = with_scope(scope_id, |scope| scope.depth) {
+        runtime()
+            .pending_scope_renders
+            .borrow_mut()
+            .insert((depth, scope_id));
+    }
+}
+
+/// Takes a scope off the render queue, returning whether it was queued
+fn unschedule(scope_id: ScopeId) -> bool {
+    with_scope(scope_id, |scope| scope.depth).is_some_and(|depth| {
+        runtime()
+            .pending_scope_renders
+            .borrow_mut()
+            .remove(&(depth, scope_id))
+    })
+}
+
+/// Makes sure queued renders get flushed: when the outermost [`batch`] ends,
+/// or in a microtask in the browser
+fn request_flush() {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    {
+        let rt = runtime();
+        if rt.batch_depth.get() == 0 && !rt.flush_scheduled.replace(true) {
+            let runtime = Runtime::current();
+            wasm_bindgen_futures::spawn_local(async move { runtime.run(flush) });
+        }
+    }
+}
+
+/// Renders every scope waiting to render because a signal it read changed.
+///
+/// Scopes render in tree order, parents before their children, so a child
+/// never renders with props its parent is about to replace. In the browser
+/// this runs on its own in a microtask after signals change. Elsewhere, call it
+/// once the changes should show.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+/// use momenta::signals::flush;
+///
+/// #[component]
+/// fn App() -> Node {
+///     let count = create_signal(0);
+///     count.set(1);
+///     rsx!(<p>{count}</p>)
+/// }
+///
+/// rsx!(<App />);
+/// flush();
+/// ```
+pub fn flush() {
+    let rt = runtime();
+    rt.flush_scheduled.set(false);
+    loop {
+        let scope_to_render = rt.pending_scope_renders.borrow_mut().pop_first();
+
+        match scope_to_render {
+            Some((_, scope_id)) if defer_memo(scope_id) => continue,
+            Some((_, scope_id)) => render_scope(scope_id),
+            None => break,
+        };
+    }
+}
+
+/// Runs `f`, holding back the renders its signal changes cause until it
+/// returns, so each affected scope renders once.
+///
+/// Renders are flushed as soon as the outermost batch ends, unless it ends
+/// while a scope renders, which leaves them to the flush running that render.
+/// Event handlers run in a batch of their own.
+///
+/// # Example
+///
+/// ```rust
+/// use momenta::prelude::*;
+/// use momenta::signals::batch;
+///
+/// #[component]
+/// fn Name() -> Node {
+///     let first = create_signal("Ada");
+///     let last = create_signal("Lovelace");
+///     // both names change before anything renders again
+///     batch(|| {
+///         first.set("Grace");
+///         last.set("Hopper");
+///     });
+///     rsx!(<p>{first} {last}</p>)
+/// }
+/// ```
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    struct BatchGuard(Rc<RuntimeState>);
+
+    impl Drop for BatchGuard {
+        fn drop(&mut self) {
+            self.0.batch_depth.set(self.0.batch_depth.get() - 1);
+        }
+    }
+
+    let rt = runtime();
+    rt.batch_depth.set(rt.batch_depth.get() + 1);
+    let guard = BatchGuard(rt);
+    let result = f();
+    drop(guard);
+
+    // a batch within a render leaves the scopes waiting to render to the
+    // flush running that render, rather than rendering them within it
+    let rt = runtime();
+    if rt.batch_depth.get() == 0 && rt.rendering_scope.get().is_none() {
+        flush();
+    }
+    result
+}
+
+/// Milliseconds since some point in time, if there is a clock
+fn now_ms() -> Option<u64> {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    return Some(js_sys::Date::now() as u64);
+    #[cfg(all(any(feature = "std", test), not(target_arch = "wasm32")))]
+    {
+        std::thread_local! {
+            static START: std::time::Instant = std::time::Instant::now();
+        }
+        return START.with(|start| Some(start.elapsed().as_millis() as u64));
+    }
+    #[allow(unreachable_code)]
+    None
+}
+
+//==============================================================================
+// RESOURCE
+//==============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceStatus {
+    Idle,
+    Pending,
+    Loading,
+    Resolved,
+}
+
+pub struct Resource<T> {
+    status: Signal<ResourceStatus>,
+    value: Signal<Option<T>>,
+}
+
+impl<T: SignalValue + PartialEq + 'static> Resource<T> {
+    pub fn status(&self) -> Signal<ResourceStatus> {
+        self.status
+    }
+
+    pub fn get(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.value.get()
+    }
+
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.value.with(|v| v.as_ref().map(f)).unwrap_or_default()
+    }
+
+    pub fn retry(&self) {
+        self.status.set(ResourceStatus::Pending);
+    }
+}
+
+#[allow(unused_variables)]
+/// Create a resource that can be asynchronously loaded
+pub fn create_resource<T, F>(fetcher: F) -> Resource<T>
+where
+    T: SignalValue + PartialEq + 'static,
+    F: AsyncFn() -> T + Send + Clone + 'static,
+    Option<T>: Copy,
+{
+    let value = create_signal(None);
+    let status = create_signal(ResourceStatus::Idle);
+
+    create_effect(move || {
+        if status.get() == ResourceStatus::Idle || status.get() == ResourceStatus::Pending {
+            status.set(ResourceStatus::Loading);
+
+            #[cfg(feature = "wasm")]
+            let fetcher = fetcher.clone();
+            #[cfg(feature = "wasm")]
+            let value = value.clone();
+            #[cfg(feature = "wasm")]
+            let runtime = Runtime::current();
+
+            #[cfg(feature = "wasm")]
+            wasm_bindgen_futures::spawn_local(async move {
+                let val = fetcher().await;
+                runtime.run(|| {
+                    batch(|| {
+                        value.set(Some(val));
+                        status.set(ResourceStatus::Resolved);
+                    })
+                });
+            });
+        }
+    });
+
+    Resource { status, value }
+}
+
+//==============================================================================
+// TESTS
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::rsx;
+    use alloc::{string::ToString, sync::Arc, vec};
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use spin::Mutex;
+
+    #[test]
+    fn test_nested_scopes() {
         run_scope(
             || {
                 let outer_signal = create_signal(0);
 
-                run_scope(
-                    move || {
-                        let inner_signal = create_signal("hello");
-                        assert!(inner_signal.get() == "hello");
-                        outer_signal.set(42);
-                        Node::Empty
-                    },
-                    |_| {},
-                );
+                run_scope(
+                    move || {
+                        let inner_signal = create_signal("hello");
+                        assert!(inner_signal.get() == "hello");
+                        outer_signal.set(42);
+                        Node::Empty
+                    },
+                    |_| {},
+                );
+
+                assert_eq!(outer_signal.get(), 42);
+                Node::Empty
+            },
+            |_| {},
+        );
+    }
+
+    #[test]
+    fn test_signal_and_effect_in_scope() {
+        run_scope(
+            move || {
+                let effect_count = Arc::new(AtomicUsize::new(0));
+                let effect_count_clone = effect_count.clone();
+                let signal = create_signal(0);
+
+                create_effect(move || {
+                    let _ = signal.get();
+                    effect_count_clone.fetch_add(1, Ordering::SeqCst);
+                    assert!(effect_count.load(Ordering::SeqCst) > 0);
+                    signal.set(1);
+                });
+
+                Node::Empty
+            },
+            |_| {},
+        );
+    }
+
+    #[test]
+    fn test_multiple_signals_and_dependencies() {
+        run_scope(
+            || {
+                let signal1 = create_signal("hello");
+                let signal2 = create_signal(0);
+
+                create_effect(move || {
+                    let _str_val = signal1.get();
+                    let _num_val = signal2.get();
+                });
+
+                signal1.set("world");
+                signal2.set(42);
+
+                assert_eq!(signal1.get(), "world");
+                assert_eq!(signal2.get(), 42);
+
+                Node::Empty
+            },
+            |_| {},
+        );
+    }
+
+    #[test]
+    fn test_keyed_scopes_survive_reorder() {
+        fn scopes(node: &Node) -> Vec<(String, ScopeId)> {
+            match node {
+                Node::Component(component) if !component.key().is_empty() => {
+                    vec![(component.key().to_string(), component.scope())]
+                }
+                Node::Component(component) => scopes(component.node()),
+                Node::Fragment(nodes) => nodes.iter().flat_map(scopes).collect(),
+                _ => Vec::new(),
+            }
+        }
+
+        let rendered = Arc::new(Mutex::new(Vec::new()));
+        let counters = Arc::new(Mutex::new(BTreeMap::new()));
+        let (rendered_clone, counters_clone) = (rendered.clone(), counters.clone());
+        let flip = Arc::new(Mutex::new(None));
+        let flip_clone = flip.clone();
+        run_scope(
+            move || {
+                let flipped = create_signal(false);
+                *flip_clone.lock() = Some(flipped);
+                let keys = if flipped.get() {
+                    ["b", "a"]
+                } else {
+                    ["a", "b"]
+                };
+                Node::Fragment(
+                    keys.into_iter()
+                        .map(|key| {
+                            let counters = counters_clone.clone();
+                            run_child_scope(
+                                Some(key.to_string()),
+                                TypeId::of::<()>(),
+                                (),
+                                |_, _| true,
+                                move |_| {
+                                    let count = create_signal(0u32);
+                                    counters.lock().insert(key, count);
+                                    Node::Text(count.get().to_string())
+                                },
+                                |_| {},
+                            )
+                        })
+                        .collect(),
+                )
+            },
+            move |node| rendered_clone.lock().push(node.clone()),
+        );
+
+        let before = scopes(&rendered.lock()[0]);
+        // signals are copied out so no lock is held while they re-render
+        let counter = counters.lock()[&"a"];
+        counter.set(5);
+        let flipped = flip.lock().unwrap();
+        flipped.set(true);
+        flush();
+
+        let after = scopes(rendered.lock().last().unwrap());
+        assert_eq!(after, [before[1].clone(), before[0].clone()]);
+        assert_eq!(counters.lock()[&"a"].get(), 5);
+    }
+
+    #[test]
+    fn test_dynamic_bindings_update_without_rendering_the_scope() {
+        let renders = Arc::new(AtomicUsize::new(0));
+        let evaluations = Arc::new(AtomicUsize::new(0));
+        let count = Arc::new(Mutex::new(None));
+        let (renders_clone, evaluations_clone, count_clone) =
+            (renders.clone(), evaluations.clone(), count.clone());
+        run_scope(
+            move || {
+                renders_clone.fetch_add(1, Ordering::SeqCst);
+                let signal = create_signal(0);
+                *count_clone.lock() = Some(signal);
+                let (text, title) = (evaluations_clone.clone(), evaluations_clone.clone());
+                rsx!(
+                    <p title={move || {
+                        title.fetch_add(1, Ordering::SeqCst);
+                        signal.get()
+                    }}>
+                        {move || {
+                            text.fetch_add(1, Ordering::SeqCst);
+                            signal.get()
+                        }}
+                    </p>
+                )
+            },
+            |_| {},
+        );
+
+        let signal = count.lock().unwrap();
+        signal.set(1);
+        flush();
+        signal.set(2);
+        flush();
+        assert_eq!(renders.load(Ordering::SeqCst), 1);
+        // both bindings ran once per value
+        assert_eq!(evaluations.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_runtimes_are_isolated() {
+        let (first, second) = (Runtime::new(), Runtime::new());
+        let counter = |runtime: &Runtime, value: i32| {
+            runtime.run(|| {
+                run_scope(
+                    move || {
+                        let count = create_signal(0);
+                        count.set(value);
+                        Node::Text(count.get().to_string())
+                    },
+                    |_| {},
+                )
+                .to_string()
+            })
+        };
+        assert_eq!(counter(&first, 1), "1");
+        assert_eq!(counter(&second, 2), "2");
+        // both runtimes handed out the same ids without clashing
+        assert_eq!(first.state.signals.borrow().len(), 1);
+        assert_eq!(second.state.signals.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_runtimes_render_in_parallel() {
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                std::thread::spawn(move || {
+                    Runtime::new().run(|| {
+                        let output = Arc::new(Mutex::new(String::new()));
+                        let output_clone = output.clone();
+                        let signal = Arc::new(Mutex::new(None));
+                        let signal_clone = signal.clone();
+                        run_scope(
+                            move || {
+                                let count = create_signal(thread);
+                                *signal_clone.lock() = Some(count);
+                                Node::Text(count.get().to_string())
+                            },
+                            move |node| *output_clone.lock() = node.to_string(),
+                        );
+                        for value in 0..100 {
+                            let count = signal.lock().unwrap();
+                            count.set(thread * 1000 + value);
+                        }
+                        flush();
+                        output.lock().clone()
+                    })
+                })
+            })
+            .collect();
+        for (thread, handle) in threads.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), (thread * 1000 + 99).to_string());
+        }
+    }
+
+    #[test]
+    fn test_dropping_a_runtime_frees_its_scopes() {
+        let captured = Arc::new(());
+        let runtime = Runtime::new();
+        let captured_clone = captured.clone();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let _ = &captured_clone;
+                    Node::Empty
+                },
+                |_| {},
+            )
+        });
+        assert_eq!(Arc::strong_count(&captured), 2);
+        drop(runtime);
+        assert_eq!(Arc::strong_count(&captured), 1);
+    }
+
+    // user code must never run while the runtime is borrowed, these used to deadlock
+
+    #[test]
+    fn test_nested_reads() {
+        Runtime::new().run(|| {
+            run_scope(
+                || {
+                    let a = create_signal(1);
+                    let b = create_signal(2);
+                    assert_eq!(a.with(|a| a + b.get()), Some(3));
+                    assert_eq!(a.with(|_| a.get()), Some(1));
+                    assert!(a != b);
+                    assert!(a == a);
+                    assert!(a < b);
+                    let c = create_signal(SignalInit::InitFn(Box::new(move || a.get() + b.get())));
+                    assert_eq!(c.get(), 3);
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+    }
+
+    #[test]
+    fn test_writes_inside_reads() {
+        Runtime::new().run(|| {
+            let signal = Arc::new(Mutex::new(None));
+            let signal_clone = signal.clone();
+            run_scope(
+                move || {
+                    *signal_clone.lock() = Some(create_signal(1));
+                    Node::Empty
+                },
+                |_| {},
+            );
+            let a = signal.lock().unwrap();
+            a.with(|value| a.set(value + 1));
+            assert_eq!(a.get(), 2);
+        });
+    }
+
+    #[test]
+    fn test_updates_inside_reads() {
+        Runtime::new().run(|| {
+            let signals = Arc::new(Mutex::new(None));
+            let signals_clone = signals.clone();
+            run_scope(
+                move || {
+                    let items = create_signal(vec![1, 2]);
+                    *signals_clone.lock() = Some((items, create_signal(false), create_signal(1)));
+                    Node::Empty
+                },
+                |_| {},
+            );
+            let (items, flag, count) = signals.lock().unwrap();
+            items.with(|snapshot| {
+                items.update(|items| items.push(3));
+                // readers keep the value they were given
+                assert_eq!(snapshot, &[1, 2]);
+            });
+            flag.with(|_| flag.toggle());
+            count.with(|_| {
+                let mut count = count;
+                count += 2;
+            });
+            assert_eq!(items.get(), [1, 2, 3]);
+            assert!(flag.get());
+            assert_eq!(count.get(), 3);
+        });
+    }
+
+    #[test]
+    fn test_panicking_updates_keep_the_value() {
+        Runtime::new().run(|| {
+            let signal = Arc::new(Mutex::new(None));
+            let signal_clone = signal.clone();
+            run_scope(
+                move || {
+                    *signal_clone.lock() = Some(create_signal(1));
+                    Node::Empty
+                },
+                |_| {},
+            );
+            let count = signal.lock().unwrap();
+            let update = std::panic::AssertUnwindSafe(|| {
+                count.update(|count| {
+                    *count += 1;
+                    panic!("update failed");
+                })
+            });
+            assert!(std::panic::catch_unwind(update).is_err());
+            assert_eq!(count.get(), 2);
+            count.set(3);
+            assert_eq!(count.get(), 3);
+        });
+    }
+
+    #[test]
+    fn test_equality_reading_signals() {
+        #[derive(Clone)]
+        struct Probe(Signal<i32>);
+
+        impl PartialEq for Probe {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.get() == other.0.get()
+            }
+        }
+
+        Runtime::new().run(|| {
+            run_scope(
+                || {
+                    let (a, b) = (create_signal(1), create_signal(2));
+                    let probe = create_signal(Probe(a));
+                    probe.set(Probe(b));
+                    assert_eq!(probe.get().0.get(), 2);
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+    }
+
+    #[test]
+    fn test_effects_creating_effects() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        Runtime::new().run(|| {
+            run_scope(
+                move || {
+                    let runs = runs_clone.clone();
+                    create_effect(move || {
+                        let runs = runs.clone();
+                        create_effect(move || {
+                            runs.fetch_add(1, Ordering::SeqCst);
+                        });
+                    });
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+        // the nested effect runs once the outer one is done
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_renders_triggered_from_callbacks() {
+        let runtime = Runtime::new();
+        let rendered = Arc::new(Mutex::new(Vec::new()));
+        let (shared, rendered_clone) = (Arc::new(Mutex::new(None)), rendered.clone());
+        let shared_clone = shared.clone();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let count = create_signal(0);
+                    *shared_clone.lock() = Some(count);
+                    Node::Text(count.get().to_string())
+                },
+                move |node| {
+                    rendered_clone.lock().push(node.to_string());
+                    // a callback writing what its own scope reads renders it again
+                    let count = shared.lock().unwrap();
+                    if count.get() < 3 {
+                        count.set(count.get() + 1);
+                    }
+                },
+            );
+            flush();
+        });
+        assert_eq!(*rendered.lock(), ["0", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_arena_reuses_freed_slots() {
+        let mut arena = Arena::default();
+        let first = arena.insert("first");
+        assert_eq!(arena.remove(first), Some("first"));
+        let second = arena.insert("second");
+        assert_eq!(first.index, second.index);
+        assert_eq!(arena.get(first), None);
+        assert_eq!(arena.remove(first), None);
+        assert_eq!(arena.get(second), Some(&"second"));
+        assert_eq!(arena.len(), 1);
+    }
+
+    type Counters = Arc<Mutex<BTreeMap<&'static str, Signal<u32>>>>;
+
+    /// Renders keyed children with a signal each, returning the signals by key
+    /// and a signal holding the keys to render
+    fn keyed_counters(keys: Vec<&'static str>) -> (Counters, Signal<Vec<&'static str>>) {
+        let counters = Arc::new(Mutex::new(BTreeMap::new()));
+        let shown = Arc::new(Mutex::new(None));
+        let (counters_clone, shown_clone) = (counters.clone(), shown.clone());
+        run_scope(
+            move || {
+                let shown = create_signal(keys.clone());
+                *shown_clone.lock() = Some(shown);
+                Node::Fragment(
+                    shown
+                        .get()
+                        .into_iter()
+                        .map(|key| {
+                            let counters = counters_clone.clone();
+                            run_child_scope(
+                                Some(key.to_string()),
+                                TypeId::of::<()>(),
+                                (),
+                                |_, _| true,
+                                move |_| {
+                                    let count = create_signal(0u32);
+                                    counters.lock().insert(key, count);
+                                    Node::Text(count.get().to_string())
+                                },
+                                |_| {},
+                            )
+                        })
+                        .collect(),
+                )
+            },
+            |_| {},
+        );
+        let shown = shown.lock().take().unwrap();
+        (counters, shown)
+    }
+
+    #[test]
+    fn test_removed_keyed_children_are_freed() {
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            let (counters, shown) = keyed_counters(vec!["a", "b"]);
+            assert_eq!(runtime.state.scopes.borrow().len(), 3);
+            assert_eq!(runtime.state.signals.borrow().len(), 3);
+
+            shown.set(vec!["a"]);
+            flush();
+            assert_eq!(runtime.state.scopes.borrow().len(), 2);
+            assert_eq!(runtime.state.signals.borrow().len(), 2);
+            let counter = counters.lock()[&"a"];
+            assert_eq!(counter.get(), 0);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "used after the scope that created it was disposed")]
+    fn test_stale_signals_are_detected() {
+        Runtime::new().run(|| {
+            let (counters, shown) = keyed_counters(vec!["a", "b"]);
+            let stale = counters.lock()[&"b"];
+            shown.set(vec!["a"]);
+            flush();
+            // "b" comes back with a new signal in the slot the old one was freed from
+            shown.set(vec!["a", "b"]);
+            flush();
+            let counter = counters.lock()[&"b"];
+            assert_eq!(counter.get(), 0);
+            stale.get();
+        });
+    }
+
+    #[test]
+    fn test_rerenders_do_not_leak_scopes() {
+        use crate::nodes::component;
+
+        #[component]
+        fn Row() -> Node {
+            let count = create_signal(0);
+            rsx!(<li title={move || count.get()}>{move || count.get()}</li>)
+        }
+
+        let runtime = Runtime::new();
+        let parent = Arc::new(Mutex::new(None));
+        let parent_clone = parent.clone();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let renders = create_signal(0);
+                    *parent_clone.lock() = Some(renders);
+                    rsx!(<ul>{renders}<Row /><Row /></ul>)
+                },
+                |_| {},
+            );
+        });
+        // the parent, and each row with its two bindings
+        assert_eq!(runtime.live_scopes(), 7);
+        let signals = runtime.state.signals.borrow().len();
+
+        let renders = parent.lock().unwrap();
+        runtime.run(|| {
+            (1..=5).for_each(|render| {
+                renders.set(render);
+                flush();
+            })
+        });
+        assert_eq!(runtime.live_scopes(), 7);
+        assert_eq!(runtime.state.signals.borrow().len(), signals);
+    }
+
+    #[test]
+    fn test_cleanups_run_on_rerender_and_disposal() {
+        let (parent_cleanups, child_cleanups) =
+            (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let show = Arc::new(Mutex::new(None));
+        let (parent_clone, child_clone, show_clone) = (
+            parent_cleanups.clone(),
+            child_cleanups.clone(),
+            show.clone(),
+        );
+        Runtime::new().run(|| {
+            run_scope(
+                move || {
+                    let shown = create_signal(true);
+                    *show_clone.lock() = Some(shown);
+                    let parent = parent_clone.clone();
+                    on_cleanup(move || {
+                        parent.fetch_add(1, Ordering::SeqCst);
+                    });
+                    if shown.get() {
+                        let child = child_clone.clone();
+                        run_scope(
+                            move || {
+                                let child = child.clone();
+                                on_cleanup(move || {
+                                    child.fetch_add(1, Ordering::SeqCst);
+                                });
+                                Node::Empty
+                            },
+                            |_| {},
+                        );
+                    }
+                    Node::Empty
+                },
+                |_| {},
+            );
+            assert_eq!(parent_cleanups.load(Ordering::SeqCst), 0);
+
+            let shown = show.lock().unwrap();
+            shown.set(false);
+            flush();
+            assert_eq!(parent_cleanups.load(Ordering::SeqCst), 1);
+            assert_eq!(child_cleanups.load(Ordering::SeqCst), 1);
+
+            shown.set(true);
+            flush();
+            assert_eq!(parent_cleanups.load(Ordering::SeqCst), 2);
+            assert_eq!(child_cleanups.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    #[test]
+    fn test_children_keep_their_state_across_parent_renders() {
+        use crate::nodes::component;
+
+        static COUNTS: Mutex<Vec<Signal<i32>>> = Mutex::new(Vec::new());
+        static RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(PartialEq)]
+        struct CounterProps {
+            label: String,
+        }
+
+        #[component]
+        fn Counter(props: &CounterProps) -> Node {
+            RENDERS.fetch_add(1, Ordering::SeqCst);
+            let count = create_signal(0);
+            COUNTS.lock().push(count);
+            let label = props.label.clone();
+            rsx!(<p>{label}{move || count.get()}</p>)
+        }
+
+        let runtime = Runtime::new();
+        let (parent, rendered) = (Arc::new(Mutex::new(None)), Arc::new(Mutex::new(Vec::new())));
+        let (parent_clone, rendered_clone) = (parent.clone(), rendered.clone());
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let renders = create_signal(0);
+                    *parent_clone.lock() = Some(renders);
+                    let label = if renders.get() < 2 { "a" } else { "b" };
+                    rsx!(<div>{renders}<Counter label={label} /><Counter label="c" /></div>)
+                },
+                move |node| rendered_clone.lock().push(node.to_string()),
+            );
+        });
+        assert_eq!(RENDERS.load(Ordering::SeqCst), 2);
+
+        let (first, renders) = (COUNTS.lock()[0], parent.lock().unwrap());
+        runtime.run(|| {
+            first.set(5);
+            // the parent renders again, but neither counter's props changed
+            renders.set(1);
+            flush();
+        });
+        assert_eq!(RENDERS.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            rendered.lock().last().unwrap(),
+            "<div>1<p>a5</p><p>c0</p></div>"
+        );
+
+        // new props render the first counter again, which keeps its signal
+        runtime.run(|| batch(|| renders.set(2)));
+        assert_eq!(RENDERS.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            rendered.lock().last().unwrap(),
+            "<div>2<p>b5</p><p>c0</p></div>"
+        );
+        assert_eq!(runtime.live_scopes(), 5);
+    }
+
+    #[test]
+    fn test_effects_run_only_when_their_inputs_change() {
+        let (renders, runs, cleanups) = (
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicUsize::new(0)),
+        );
+        let signals = Arc::new(Mutex::new(None));
+        let (renders_clone, runs_clone, cleanups_clone, signals_clone) = (
+            renders.clone(),
+            runs.clone(),
+            cleanups.clone(),
+            signals.clone(),
+        );
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    renders_clone.fetch_add(1, Ordering::SeqCst);
+                    let (input, other, shown) =
+                        (create_signal(0), create_signal(0), create_signal(true));
+                    *signals_clone.lock() = Some((input, other, shown));
+                    let _ = other.get();
+                    if shown.get() {
+                        let (runs, cleanups) = (runs_clone.clone(), cleanups_clone.clone());
+                        create_effect(move || {
+                            let _ = input.get();
+                            runs.fetch_add(1, Ordering::SeqCst);
+                            let cleanups = cleanups.clone();
+                            move || {
+                                cleanups.fetch_add(1, Ordering::SeqCst);
+                            }
+                        });
+                    }
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        let (input, other, shown) = signals.lock().unwrap();
+        runtime.run(|| {
+            // the scope renders again but the effect's input didn't change
+            other.set(1);
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 2);
+            assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+            // the effect runs again without the scope rendering
+            input.set(1);
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 2);
+            assert_eq!(runs.load(Ordering::SeqCst), 2);
+            assert_eq!(cleanups.load(Ordering::SeqCst), 1);
+
+            // the effect is disposed once the scope no longer creates it
+            shown.set(false);
+            flush();
+            assert_eq!(runs.load(Ordering::SeqCst), 2);
+            assert_eq!(cleanups.load(Ordering::SeqCst), 2);
+            input.set(2);
+            flush();
+            assert_eq!(runs.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn test_memos_notify_only_when_their_value_changes() {
+        let (renders, computes) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let (input, rendered) = (
+            Arc::new(Mutex::new(None)),
+            Arc::new(Mutex::new(String::new())),
+        );
+        let (renders_clone, computes_clone, input_clone, rendered_clone) = (
+            renders.clone(),
+            computes.clone(),
+            input.clone(),
+            rendered.clone(),
+        );
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            let count = run_scope(
+                move || {
+                    let count = create_signal(1);
+                    *input_clone.lock() = Some(count);
+                    let computes = computes_clone.clone();
+                    let parity = create_memo(move || {
+                        computes.fetch_add(1, Ordering::SeqCst);
+                        if count.get() % 2 == 0 { "even" } else { "odd" }
+                    });
+                    let (renders, rendered) = (renders_clone.clone(), rendered_clone.clone());
+                    run_scope(
+                        move || {
+                            renders.fetch_add(1, Ordering::SeqCst);
+                            rsx!(<p>{parity}</p>)
+                        },
+                        move |node| *rendered.lock() = node.to_string(),
+                    )
+                },
+                |_| {},
+            );
+            assert_eq!(count.to_string(), "<p>odd</p>");
+        });
+
+        let count = input.lock().unwrap();
+        runtime.run(|| batch(|| count.set(3)));
+        assert_eq!(computes.load(Ordering::SeqCst), 2);
+        assert_eq!(renders.load(Ordering::SeqCst), 1);
 
-                assert_eq!(outer_signal.get(), 42);
-                Node::Empty
-            },
-            |_| {},
+        runtime.run(|| batch(|| count.set(4)));
+        assert_eq!(computes.load(Ordering::SeqCst), 3);
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+        assert_eq!(*rendered.lock(), "<p>even</p>");
+    }
+
+    #[test]
+    fn test_memos_are_computed_lazily() {
+        let computes = Arc::new(AtomicUsize::new(0));
+        let memo = Arc::new(Mutex::new(None));
+        let (computes_clone, memo_clone) = (computes.clone(), memo.clone());
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let count = create_signal(1);
+                    let computes = computes_clone.clone();
+                    let doubled = create_memo(move || {
+                        computes.fetch_add(1, Ordering::SeqCst);
+                        count.get() * 2
+                    });
+                    *memo_clone.lock() = Some((count, doubled));
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+        assert_eq!(computes.load(Ordering::SeqCst), 0);
+
+        let (count, doubled) = memo.lock().unwrap();
+        runtime.run(|| {
+            assert_eq!(doubled.get(), 2);
+            assert_eq!(doubled.get(), 2);
+            assert_eq!(computes.load(Ordering::SeqCst), 1);
+
+            // nothing reads the memo from a scope, so changes only mark it stale
+            count.set(2);
+            count.set(3);
+            flush();
+            assert_eq!(computes.load(Ordering::SeqCst), 1);
+            assert!(doubled == 6);
+            assert_eq!(computes.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn test_memos_computed_during_renders_drop_their_old_dependencies() {
+        let computes = Arc::new(AtomicUsize::new(0));
+        let signals = Arc::new(Mutex::new(None));
+        let (computes_clone, signals_clone) = (computes.clone(), signals.clone());
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let (shown, flag) = (create_signal(true), create_signal(true));
+                    let (a, b) = (create_signal(1), create_signal(2));
+                    let computes = computes_clone.clone();
+                    let memo = create_memo(move || {
+                        computes.fetch_add(1, Ordering::SeqCst);
+                        if flag.get() { a.get() } else { b.get() }
+                    });
+                    *signals_clone.lock() = Some((shown, flag, a));
+                    // the memo is computed while this scope renders
+                    match shown.get() {
+                        true => Node::Text(memo.get().to_string()),
+                        false => Node::Empty,
+                    }
+                },
+                |_| {},
+            );
+        });
+        assert_eq!(computes.load(Ordering::SeqCst), 1);
+
+        let (shown, flag, a) = signals.lock().unwrap();
+        runtime.run(|| {
+            shown.set(false);
+            flush();
+            // nothing reads the memo, so it's only computed once shown again
+            flag.set(false);
+            flush();
+            assert_eq!(computes.load(Ordering::SeqCst), 1);
+            shown.set(true);
+            flush();
+            assert_eq!(computes.load(Ordering::SeqCst), 2);
+
+            // the memo reads `b` now
+            a.set(3);
+            flush();
+            assert_eq!(computes.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn test_memos_stay_in_their_runtime() {
+        let (first, second) = (Runtime::new(), Runtime::new());
+        let (_root, memo) = first.run(|| create_root(|| create_memo(|| 100)));
+        // the same slots in another runtime
+        let (_other, other) = second.run(|| create_root(|| create_memo(|| 300)));
+        first.run(|| assert_eq!(memo.try_get(), Ok(100)));
+        second.run(|| {
+            assert_eq!(other.get(), 300);
+            assert_eq!(memo.try_get(), Err(SignalError::MissingRuntime));
+            let panic = std::panic::catch_unwind(|| memo.get()).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(message.contains("outside of the runtime"));
+        });
+    }
+
+    #[test]
+    fn test_batched_changes_render_once() {
+        let renders = Arc::new(AtomicUsize::new(0));
+        let signals = Arc::new(Mutex::new(None));
+        let (renders_clone, signals_clone) = (renders.clone(), signals.clone());
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    renders_clone.fetch_add(1, Ordering::SeqCst);
+                    let (first, last) = (create_signal("Ada"), create_signal("Lovelace"));
+                    *signals_clone.lock() = Some((first, last));
+                    Node::Text(alloc::format!("{} {}", first.get(), last.get()))
+                },
+                |_| {},
+            );
+        });
+
+        let (first, last) = signals.lock().unwrap();
+        runtime.run(|| {
+            let name = batch(|| {
+                first.set("Grace");
+                last.set("Hopper");
+                // nothing renders until the batch ends
+                assert_eq!(renders.load(Ordering::SeqCst), 1);
+                batch(|| first.set("Grace"));
+                assert_eq!(renders.load(Ordering::SeqCst), 1);
+                alloc::format!("{} {}", first.get(), last.get())
+            });
+            assert_eq!(name, "Grace Hopper");
+            assert_eq!(renders.load(Ordering::SeqCst), 2);
+
+            // without a batch, changes wait for the next flush
+            first.set("Alan");
+            last.set("Turing");
+            assert_eq!(renders.load(Ordering::SeqCst), 2);
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 3);
+        });
+    }
+
+    #[test]
+    fn test_batches_within_renders_leave_other_scopes_to_the_flush() {
+        let (rendering, nested) = (
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicBool::new(false)),
         );
+        let signal = Arc::new(Mutex::new(None));
+        let (rendering_clone, nested_clone, signal_clone) =
+            (rendering.clone(), nested.clone(), signal.clone());
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let count = create_signal(0);
+                    *signal_clone.lock() = Some(count);
+                    let rendering = rendering_clone.clone();
+                    run_scope(
+                        move || {
+                            count.get();
+                            rendering.store(true, Ordering::SeqCst);
+                            batch(|| ());
+                            rendering.store(false, Ordering::SeqCst);
+                            Node::Empty
+                        },
+                        |_| {},
+                    );
+                    let (rendering, nested) = (rendering_clone.clone(), nested_clone.clone());
+                    run_scope(
+                        move || {
+                            count.get();
+                            nested.fetch_or(rendering.load(Ordering::SeqCst), Ordering::SeqCst);
+                            Node::Empty
+                        },
+                        |_| {},
+                    );
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+
+        // both scopes render again, the second one only once the first is done
+        let count = signal.lock().unwrap();
+        runtime.run(|| count.set(1));
+        runtime.run(flush);
+        assert!(!nested.load(Ordering::SeqCst));
     }
 
     #[test]
-    fn test_signal_and_effect_in_scope() {
-        run_scope(
-            move || {
-                let effect_count = Arc::new(AtomicUsize::new(0));
-                let effect_count_clone = effect_count.clone();
-                let signal = create_signal(0);
+    fn test_changes_made_before_a_nested_render_are_kept() {
+        let runtime = Runtime::new();
+        let (_root, value) = runtime.run(|| create_root(|| create_signal(0)));
+        let renders = Arc::new(AtomicUsize::new(0));
+        let renders_clone = renders.clone();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    renders_clone.fetch_add(1, Ordering::SeqCst);
+                    Node::Text(value.get().to_string())
+                },
+                |_| {},
+            );
+            // sets the signal during its own render, then renders a child
+            run_scope(
+                move || {
+                    value.set(1);
+                    run_scope(|| Node::Empty, |_| {});
+                    Node::Empty
+                },
+                |_| {},
+            );
+            flush();
+        });
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+    }
 
-                create_effect(move || {
-                    let _ = signal.get();
-                    effect_count_clone.fetch_add(1, Ordering::SeqCst);
-                    assert!(effect_count.load(Ordering::SeqCst) > 0);
-                    signal.set(1);
-                });
+    #[test]
+    fn test_flush_renders_parents_before_children() {
+        use crate::nodes::component;
 
-                Node::Empty
-            },
-            |_| {},
+        static SEEN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+        static COUNT: Mutex<Option<Signal<i32>>> = Mutex::new(None);
+
+        #[derive(PartialEq)]
+        struct LabelProps {
+            label: String,
+        }
+
+        #[component]
+        fn Label(props: &LabelProps) -> Node {
+            let count = create_signal(0);
+            *COUNT.lock() = Some(count);
+            SEEN.lock()
+                .push(alloc::format!("{}{}", props.label, count.get()));
+            Node::Empty
+        }
+
+        let runtime = Runtime::new();
+        let parent = Arc::new(Mutex::new(None));
+        let parent_clone = parent.clone();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let label = create_signal("a");
+                    *parent_clone.lock() = Some(label);
+                    rsx!(<div><Label label={label.get()} /></div>)
+                },
+                |_| {},
+            );
+        });
+
+        let (label, count) = (parent.lock().unwrap(), COUNT.lock().unwrap());
+        runtime.run(|| {
+            batch(|| {
+                // the child is queued first, but its parent renders before it
+                count.set(1);
+                label.set("b");
+            })
+        });
+        // the child renders once, with both its new props and its new state
+        assert_eq!(*SEEN.lock(), ["a0", "b1"]);
+    }
+
+    #[test]
+    fn test_untracked_reads_do_not_subscribe() {
+        let (renders, runs) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let signals = Arc::new(Mutex::new(None));
+        let (renders_clone, runs_clone, signals_clone) =
+            (renders.clone(), runs.clone(), signals.clone());
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    renders_clone.fetch_add(1, Ordering::SeqCst);
+                    let (config, input) = (create_signal(1), create_signal(1));
+                    *signals_clone.lock() = Some((config, input));
+                    let _ = config.peek() + config.get_untracked();
+                    let runs = runs_clone.clone();
+                    create_effect(move || {
+                        // only `input` re-runs the effect
+                        let _ = input.get() * untrack(|| config.get());
+                        runs.fetch_add(1, Ordering::SeqCst);
+                    });
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+
+        let (config, input) = signals.lock().unwrap();
+        runtime.run(|| {
+            config.set(2);
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 1);
+            assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+            input.set(2);
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 1);
+            assert_eq!(runs.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn test_updates_mutate_values_in_place() {
+        static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, PartialEq)]
+        struct Item;
+
+        impl Clone for Item {
+            fn clone(&self) -> Self {
+                CLONES.fetch_add(1, Ordering::SeqCst);
+                Item
+            }
+        }
+
+        let renders = Arc::new(AtomicUsize::new(0));
+        let signals = Arc::new(Mutex::new(None));
+        let (renders_clone, signals_clone) = (renders.clone(), signals.clone());
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    renders_clone.fetch_add(1, Ordering::SeqCst);
+                    let (items, count) = (create_signal(vec![Item, Item]), create_signal(0));
+                    let _ = (items.len(), count.get());
+                    *signals_clone.lock() = Some((items, count));
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+
+        let (items, mut count) = signals.lock().take().unwrap();
+        runtime.run(|| {
+            items.push(Item);
+            assert_eq!(items.with_mut(|items| items.len()), 3);
+            assert_eq!(items.pop(), Some(Item));
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 2);
+            assert_eq!(CLONES.load(Ordering::SeqCst), 0);
+
+            // nothing to clear, so nothing to render
+            items.clear();
+            items.clear();
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 3);
+            assert!(!items.update_if(|items| !items.is_empty()));
+
+            count += 2;
+            count.update(|count| *count *= 3);
+            flush();
+            assert_eq!(count.get(), 6);
+            assert_eq!(renders.load(Ordering::SeqCst), 4);
+        });
+    }
+
+    #[test]
+    fn test_operators_notify_only_on_changes() {
+        // only `Add`, without `AddAssign`
+        #[derive(Clone, Debug, PartialEq)]
+        struct Meters(u32);
+
+        impl core::ops::Add for Meters {
+            type Output = Meters;
+            fn add(self, rhs: Meters) -> Meters {
+                Meters(self.0 + rhs.0)
+            }
+        }
+
+        let renders = Arc::new(AtomicUsize::new(0));
+        let signal = Arc::new(Mutex::new(None));
+        let (renders_clone, signal_clone) = (renders.clone(), signal.clone());
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    renders_clone.fetch_add(1, Ordering::SeqCst);
+                    let distance = create_signal(Meters(1));
+                    let _ = distance.get();
+                    *signal_clone.lock() = Some(distance);
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+
+        let mut distance = signal.lock().take().unwrap();
+        runtime.run(|| {
+            distance += Meters(0);
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 1);
+
+            distance += Meters(2);
+            flush();
+            assert_eq!(distance.get(), Meters(3));
+            assert_eq!(renders.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "hook order changed")]
+    fn test_conditional_hooks_are_detected() {
+        let show = Arc::new(Mutex::new(None));
+        let show_clone = show.clone();
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let shown = create_signal(false);
+                    *show_clone.lock() = Some(shown);
+                    if shown.get() {
+                        create_effect(|| {});
+                    }
+                    let _ = create_signal("label");
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+
+        let shown = show.lock().unwrap();
+        runtime.run(|| {
+            shown.set(true);
+            flush();
+        });
+    }
+
+    #[test]
+    fn test_contexts_resolve_to_the_nearest_provider() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (seen_clone, shadow) = (seen.clone(), Arc::new(Mutex::new(None)));
+        let shadow_clone = shadow.clone();
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    provide_context("root");
+                    provide_context(1);
+                    let (seen, shadow) = (seen_clone.clone(), shadow_clone.clone());
+                    run_scope(
+                        move || {
+                            let shadowed = create_signal(true);
+                            *shadow.lock() = Some(shadowed);
+                            if shadowed.get() {
+                                provide_context("middle");
+                            }
+                            let seen = seen.clone();
+                            run_scope(
+                                move || {
+                                    seen.lock().push((
+                                        use_context::<&str>(),
+                                        use_context::<i32>(),
+                                        use_context::<bool>(),
+                                    ));
+                                    Node::Empty
+                                },
+                                |_| {},
+                            );
+                            Node::Empty
+                        },
+                        |_| {},
+                    );
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+
+        let shadowed = shadow.lock().unwrap();
+        runtime.run(|| {
+            assert_eq!(use_context::<&str>(), None);
+            shadowed.set(false);
+            flush();
+        });
+        assert_eq!(
+            *seen.lock(),
+            [
+                (Some("middle"), Some(1), None),
+                (Some("root"), Some(1), None)
+            ]
         );
     }
 
     #[test]
-    fn test_multiple_signals_and_dependencies() {
-        run_scope(
-            || {
-                let signal1 = create_signal("hello");
-                let signal2 = create_signal(0);
+    fn test_stores_notify_per_field() {
+        use momenta_macros::Store;
+
+        #[derive(Store)]
+        struct User {
+            name: &'static str,
+            age: u32,
+        }
+
+        #[derive(Store)]
+        struct AppState {
+            #[store]
+            user: User,
+            todos: Vec<&'static str>,
+        }
 
+        let (names, todos) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let store = Arc::new(Mutex::new(None));
+        let firsts = Arc::new(AtomicUsize::new(0));
+        let (names_clone, todos_clone, store_clone) = (names.clone(), todos.clone(), store.clone());
+        let firsts_clone = firsts.clone();
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            run_scope(
+                move || {
+                    let state = create_store(AppState {
+                        user: User {
+                            name: "Ada",
+                            age: 36,
+                        },
+                        todos: vec!["docs"],
+                    });
+                    *store_clone.lock() = Some(state);
+                    let (names, todos) = (names_clone.clone(), todos_clone.clone());
+                    let name = state.user().name();
+                    let list = state.todos();
+                    let first = list.at(0);
+                    let firsts = firsts_clone.clone();
+                    rsx!(
+                        <div>
+                            {move || {
+                                names.fetch_add(1, Ordering::SeqCst);
+                                name.get()
+                            }}
+                            {move || {
+                                todos.fetch_add(1, Ordering::SeqCst);
+                                list.len().to_string()
+                            }}
+                            {move || {
+                                firsts.fetch_add(1, Ordering::SeqCst);
+                                first.get().unwrap_or_default()
+                            }}
+                        </div>
+                    )
+                },
+                |_| {},
+            );
+        });
+
+        let state = store.lock().take().unwrap();
+        runtime.run(|| {
+            state.user().age().set(37);
+            flush();
+            assert_eq!(names.load(Ordering::SeqCst), 1);
+            assert_eq!(todos.load(Ordering::SeqCst), 1);
+
+            state.todos().push("tests");
+            flush();
+            assert_eq!(names.load(Ordering::SeqCst), 1);
+            assert_eq!(todos.load(Ordering::SeqCst), 2);
+
+            // readers of an item only render again when that item changes
+            assert!(state.todos().set_at(1, "more tests"));
+            flush();
+            assert_eq!(firsts.load(Ordering::SeqCst), 1);
+            assert!(state.todos().set_at(0, "guides"));
+            flush();
+            assert_eq!(firsts.load(Ordering::SeqCst), 2);
+            assert_eq!(todos.load(Ordering::SeqCst), 4);
+            assert!(!state.todos().set_at(2, "release"));
+
+            // setting the whole state only notifies the fields that changed
+            state.set(AppState {
+                user: User {
+                    name: "Grace",
+                    age: 37,
+                },
+                todos: vec!["guides", "more tests"],
+            });
+            assert_eq!(names.load(Ordering::SeqCst), 2);
+            assert_eq!(todos.load(Ordering::SeqCst), 4);
+            assert_eq!(state.user().name().get(), "Grace");
+        });
+    }
+
+    #[test]
+    fn test_any_send_type_can_be_stored() {
+        use alloc::borrow::Cow;
+
+        Runtime::new().run(|| {
+            run_scope(
+                || {
+                    let pair = create_signal((1, "one"));
+                    let map = create_signal(BTreeMap::from([(1, [0u8; 4])]));
+                    let shared = create_signal(Arc::new(Box::new(Cow::Borrowed("shared"))));
+                    let parsed = create_signal("42".parse::<i32>());
+                    pair.set((2, "two"));
+                    assert_eq!(pair.get(), (2, "two"));
+                    assert_eq!(map.with(|map| map[&1]), Some([0; 4]));
+                    assert_eq!(shared.get().as_ref().as_ref(), "shared");
+                    assert_eq!(parsed.get(), Ok(42));
+                    Node::Empty
+                },
+                |_| {},
+            );
+        });
+    }
+
+    #[test]
+    fn test_local_signals_hold_values_that_are_not_send() {
+        use alloc::rc::Rc;
+
+        let output = Arc::new(Mutex::new(String::new()));
+        let output_clone = output.clone();
+        let runtime = Runtime::new();
+        let signal = runtime.run(|| {
+            let signal = Arc::new(Mutex::new(None));
+            let signal_clone = signal.clone();
+            run_scope(
+                move || {
+                    let shared = create_local_signal(Rc::new(1));
+                    *signal_clone.lock() = Some(shared);
+                    let doubled = create_memo(move || *shared.get() * 2);
+                    rsx!(<p>{doubled}</p>)
+                },
+                move |node| *output_clone.lock() = node.to_string(),
+            );
+            let shared = signal.lock().unwrap();
+            shared.set(Rc::new(2));
+            flush();
+            shared
+        });
+        assert_eq!(*output.lock(), "<p>4</p>");
+
+        // the handle is `Send`, but the value stays with its runtime
+        let used_elsewhere =
+            std::thread::spawn(move || std::panic::catch_unwind(|| signal.with(|_| ())).is_err());
+        assert!(used_elsewhere.join().unwrap());
+        let other_runtime = std::panic::catch_unwind(|| Runtime::new().run(|| signal.get()));
+        assert!(other_runtime.is_err());
+    }
+
+    #[test]
+    fn test_roots_own_signals_outside_of_components() {
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            assert!(matches!(
+                try_create_signal(0),
+                Err(SignalCreationError::OutsideScope)
+            ));
+
+            let runs = Arc::new(AtomicUsize::new(0));
+            let runs_clone = runs.clone();
+            let (root, count) = create_root(move || {
+                let count = create_signal(0);
                 create_effect(move || {
-                    let _str_val = signal1.get();
-                    let _num_val = signal2.get();
+                    let _ = count.get();
+                    runs_clone.fetch_add(1, Ordering::SeqCst);
                 });
+                // not read by the root itself, and its effect only runs once
+                // the root is set up
+                count.set(1);
+                count
+            });
+            flush();
+            assert_eq!(runs.load(Ordering::SeqCst), 1);
 
-                signal1.set("world");
-                signal2.set(42);
+            let rendered = Arc::new(Mutex::new(String::new()));
+            let rendered_clone = rendered.clone();
+            run_scope(
+                move || Node::Text(count.get().to_string()),
+                move |node| *rendered_clone.lock() = node.to_string(),
+            );
+            count.set(2);
+            flush();
+            assert_eq!(*rendered.lock(), "2");
+            assert_eq!(runs.load(Ordering::SeqCst), 2);
 
-                assert_eq!(signal1.get(), "world");
-                assert_eq!(signal2.get(), 42);
+            let more = root.run(|| try_create_signal("more"));
+            assert!(more.is_ok());
+            let live = runtime.live_scopes();
+            drop(root);
+            // the root and its effect
+            assert_eq!(runtime.live_scopes(), live - 2);
+            assert!(std::panic::catch_unwind(|| count.get()).is_err());
+        });
+    }
 
-                Node::Empty
-            },
-            |_| {},
+    #[test]
+    fn test_fallible_access_reports_why_it_failed() {
+        let runtime = Runtime::new();
+        let (root, (count, doubled)) = runtime.run(|| {
+            create_root(|| {
+                let count = create_signal(1);
+                (count, create_memo(move || count.get() * 2))
+            })
+        });
+        runtime.run(|| {
+            assert_eq!(count.try_get(), Ok(1));
+            assert_eq!(count.try_set(2), Ok(()));
+            assert_eq!(count.try_with(|count| count * 2), Ok(4));
+            assert_eq!(doubled.try_get(), Ok(4));
+
+            // the value is out of its slot while it's being mutated
+            count.update(|_| {
+                assert_eq!(count.try_get(), Err(SignalError::BeingMutated));
+                assert_eq!(count.try_set(3), Err(SignalError::BeingMutated));
+            });
+            let panic = std::panic::catch_unwind(|| count.with_mut(|_| count.get())).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(message.contains("being mutated"));
+            assert_eq!(count.try_get(), Ok(2));
+        });
+        assert_eq!(count.try_get(), Err(SignalError::MissingRuntime));
+        assert_eq!(doubled.try_get(), Err(SignalError::MissingRuntime));
+
+        drop(root);
+        runtime.run(|| {
+            assert_eq!(count.try_get(), Err(SignalError::Disposed));
+            assert_eq!(count.try_set(3), Err(SignalError::Disposed));
+            assert_eq!(doubled.try_with(|_| ()), Err(SignalError::Disposed));
+            assert!(std::panic::catch_unwind(|| doubled.get()).is_err());
+            let panic = std::panic::catch_unwind(|| count.get()).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(message.contains("disposed"));
+            #[cfg(debug_assertions)]
+            assert!(message.contains(file!()));
+        });
+    }
+
+    #[test]
+    fn test_signal_vec_views_follow_each_operation() {
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            let (_root, (numbers, doubled, odd, sorted)) = create_root(|| {
+                let numbers = create_signal_vec(vec![3, 1, 2]);
+                let doubled = numbers.map(|n| n * 2);
+                let odd = numbers.filter(|n| n % 2 == 1);
+                let sorted = numbers.sort_by(|a, b| a.cmp(b));
+                (numbers, doubled, odd, sorted)
+            });
+            assert_eq!(sorted.get(), [1, 2, 3]);
+            let (version, sorted_version) = (numbers.version(), sorted.version());
+
+            numbers.push(5);
+            numbers.insert(0, 4);
+            numbers.move_item(0, 2);
+            numbers.update_at(1, |n| *n = 6);
+            numbers.set_at(0, 0);
+            assert_eq!(numbers.remove(3), 2);
+            flush();
+
+            let items = numbers.get();
+            assert_eq!(items, [0, 6, 4, 5]);
+            assert_eq!(doubled.get(), [0, 12, 8, 10]);
+            assert_eq!(odd.get(), [5]);
+            assert_eq!(sorted.get(), [0, 4, 5, 6]);
+            assert_eq!(
+                numbers.diffs_since(version).unwrap()[..3],
+                [
+                    VecDiff::InsertAt { index: 3, value: 5 },
+                    VecDiff::InsertAt { index: 0, value: 4 },
+                    VecDiff::Move { from: 0, to: 2 },
+                ]
+            );
+            // views apply the operations rather than starting over
+            assert!(
+                !sorted
+                    .diffs_since(sorted_version)
+                    .unwrap()
+                    .contains(&VecDiff::Clear)
+            );
+
+            // an index past the end moves nothing
+            let version = numbers.version();
+            let panic = std::panic::catch_unwind(|| numbers.move_item(0, 4)).unwrap_err();
+            let message = panic.downcast_ref::<String>().unwrap();
+            assert!(message.contains("cannot move item 0 to 4"));
+            assert_eq!(numbers.get(), [0, 6, 4, 5]);
+            assert_eq!(numbers.version(), version);
+
+            numbers.clear();
+            flush();
+            assert!(doubled.is_empty() && odd.is_empty() && sorted.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_for_renders_only_changed_rows() {
+        use crate::dom::For;
+
+        let renders = Arc::new(AtomicUsize::new(0));
+        let items = Arc::new(Mutex::new(None));
+        let (renders_clone, items_clone) = (renders.clone(), items.clone());
+        let runtime = Runtime::new();
+        let scope = runtime.run(|| {
+            let node = run_scope(
+                move || {
+                    let names = create_signal_vec(vec!["ada", "grace"]);
+                    *items_clone.lock() = Some(names);
+                    let renders = renders_clone.clone();
+                    rsx!(
+                        <ul>
+                            <For each={names} key={|name| name.to_string()}>
+                                {move |name| {
+                                    renders.fetch_add(1, Ordering::SeqCst);
+                                    rsx!(<li>{*name}</li>)
+                                }}
+                            </For>
+                        </ul>
+                    )
+                },
+                |_| {},
+            );
+            let Node::Component(component) = node else {
+                panic!("expected a scope")
+            };
+            component.scope()
+        });
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+
+        let names = items.lock().take().unwrap();
+        let output = || latest_output(scope).unwrap().to_string();
+        runtime.run(|| {
+            names.push("alan");
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 3);
+
+            names.move_item(2, 0);
+            names.remove(1);
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 3);
+            assert_eq!(output(), "<ul><li>alan</li><li>grace</li></ul>");
+
+            names.set_at(1, "grace");
+            flush();
+            assert_eq!(renders.load(Ordering::SeqCst), 4);
+        });
+    }
+
+    #[test]
+    fn test_for_renders_rows_again_with_its_parent() {
+        use crate::dom::For;
+
+        let signals = Arc::new(Mutex::new(None));
+        let signals_clone = signals.clone();
+        let runtime = Runtime::new();
+        let scope = runtime.run(|| {
+            let node = run_scope(
+                move || {
+                    let (prefix, second) = (create_signal("-"), create_signal(false));
+                    let names = create_signal_vec(vec!["ada", "grace"]);
+                    let others = create_signal_vec(vec!["alan"]);
+                    *signals_clone.lock() = Some((prefix, second, names));
+                    let prefix = prefix.get();
+                    let each = if second.get() { others } else { names };
+                    rsx!(
+                        <ul>
+                            <For each={each} key={|name| name.to_string()}>
+                                {move |name| rsx!(<li>{prefix}{*name}</li>)}
+                            </For>
+                        </ul>
+                    )
+                },
+                |_| {},
+            );
+            let Node::Component(component) = node else {
+                panic!("expected a scope")
+            };
+            component.scope()
+        });
+
+        let (prefix, second, names) = signals.lock().take().unwrap();
+        let output = || latest_output(scope).unwrap().to_string();
+        runtime.run(|| {
+            // rows capture the state of the render that created them
+            prefix.set("*");
+            flush();
+            assert_eq!(output(), "<ul><li>*ada</li><li>*grace</li></ul>");
+
+            names.push("edsger");
+            flush();
+            assert_eq!(
+                output(),
+                "<ul><li>*ada</li><li>*grace</li><li>*edsger</li></ul>"
+            );
+
+            // another vector is followed from scratch
+            second.set(true);
+            flush();
+            assert_eq!(output(), "<ul><li>*alan</li></ul>");
+        });
+    }
+
+    #[test]
+    fn test_signal_halves_share_one_value() {
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            let (_root, (count, mut set_count, flag)) = create_root(|| {
+                let (count, set_count) = create_signal_pair(1);
+                (count, set_count, create_signal(false).split())
+            });
+            set_count += 2;
+            set_count.update(|count| *count *= 2);
+            assert_eq!(count.get(), 6);
+            assert!(count == 6 && count > 5);
+
+            let (enabled, set_enabled) = flag;
+            set_enabled.toggle();
+            assert_eq!(enabled.then(|| "on"), Some("on"));
+            // halves of the same signal are the same handle
+            assert!(set_enabled == WriteSignal::from(enabled.signal));
+        });
+    }
+
+    #[test]
+    fn test_subscriptions_and_streams_follow_changes() {
+        use core::task::{Context, Poll, Waker};
+
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            let (root, count) = create_root(|| create_signal(0));
+            let changes = Arc::new(Mutex::new(Vec::new()));
+            let log = changes.clone();
+            let subscription = count.subscribe(move |new, old| log.lock().push((*old, *new)));
+            let mut stream = count.to_stream();
+            let mut next = || {
+                let mut cx = Context::from_waker(Waker::noop());
+                Pin::new(&mut stream).poll_next(&mut cx)
+            };
+            assert_eq!(next(), Poll::Ready(Some(0)));
+            assert_eq!(next(), Poll::Pending);
+
+            batch(|| {
+                count.set(1);
+                count.set(2);
+            });
+            count.set(3);
+            flush();
+            assert_eq!(*changes.lock(), [(0, 2), (2, 3)]);
+            // streams only yield the latest value
+            assert_eq!(next(), Poll::Ready(Some(3)));
+            assert_eq!(next(), Poll::Pending);
+
+            // a batch that ends where it started changes nothing
+            batch(|| {
+                count.set(4);
+                count.set(3);
+            });
+            flush();
+            assert_eq!(changes.lock().len(), 2);
+            assert_eq!(next(), Poll::Pending);
+
+            let scopes = runtime.live_scopes();
+            drop(subscription);
+            assert_eq!(runtime.live_scopes(), scopes - 1);
+            count.set(4);
+            flush();
+            assert_eq!(changes.lock().len(), 2);
+
+            // the stream ends along with the signal
+            drop(root);
+            assert_eq!(next(), Poll::Ready(Some(4)));
+            assert_eq!(next(), Poll::Ready(None));
+        });
+    }
+
+    #[test]
+    fn test_streams_feed_signals() {
+        use core::task::{Context, Poll, Waker};
+
+        struct Values(Vec<i32>);
+
+        impl Stream for Values {
+            type Item = i32;
+
+            fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<i32>> {
+                Poll::Ready(self.0.pop())
+            }
+        }
+
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            let (root, count) = create_root(|| create_signal(0));
+            let mut feed = core::pin::pin!(count.feed(Values(vec![3, 2, 1])));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert_eq!(feed.as_mut().poll(&mut cx), Poll::Ready(()));
+            assert_eq!(count.get(), 3);
+
+            // values of a disposed signal go nowhere, and end the feed
+            drop(root);
+            let mut feed = core::pin::pin!(count.feed(Values(vec![5, 4])));
+            assert_eq!(feed.as_mut().poll(&mut cx), Poll::Ready(()));
+        });
+    }
+
+    #[test]
+    fn test_history_undoes_steps() {
+        let runtime = Runtime::new();
+        runtime.run(|| {
+            let (_root, (count, typed)) = create_root(|| {
+                let options = HistoryOptions {
+                    limit: 2,
+                    ..Default::default()
+                };
+                let coalesced = HistoryOptions {
+                    coalesce_ms: 60_000,
+                    ..Default::default()
+                };
+                (
+                    create_history_signal(0, options),
+                    create_history_signal(String::new(), coalesced),
+                )
+            });
+            assert!(!count.can_undo().get());
+            count.set(1);
+            count.set(1);
+            count.set(2);
+            count.checkpoint(|| {
+                count.set(3);
+                count.update(|count| *count += 1);
+            });
+            assert!(count.can_undo().get());
+
+            // the oldest step went past the limit
+            assert!(count.undo() && count.undo());
+            assert_eq!(count.get(), 1);
+            assert!(!count.undo() && !count.can_undo().get());
+            assert!(count.can_redo().get());
+            assert!(count.redo());
+            assert_eq!(count.get(), 2);
+            // a change drops what was undone
+            count.set(5);
+            assert!(!count.redo() && !count.can_redo().get());
+
+            for key in ["a", "b", "c"] {
+                typed.update(|text| text.push_str(key));
+            }
+            assert!(typed.undo());
+            assert_eq!(typed.get(), "");
+
+            // a checkpoint that panics still ends, so later changes are steps
+            // of their own
+            let checkpoint = std::panic::AssertUnwindSafe(|| {
+                count.checkpoint(|| {
+                    count.set(6);
+                    panic!("checkpoint failed");
+                })
+            });
+            assert!(std::panic::catch_unwind(checkpoint).is_err());
+            count.set(7);
+            count.set(8);
+            assert!(count.undo());
+            assert_eq!(count.get(), 7);
+        });
+    }
+
+    #[test]
+    fn test_recordings_replay_the_same_renders() {
+        use alloc::format;
+
+        type Signals = (Signal<&'static str>, Signal<i32>, Signal<bool>);
+        static SIGNALS: spin::Mutex<Option<Signals>> = spin::Mutex::new(None);
+        static CHILD: spin::Mutex<Option<(ScopeId, Signal<i32>)>> = spin::Mutex::new(None);
+
+        fn app() -> Node {
+            let (name, count, shown) =
+                (create_signal("ada"), create_signal(0), create_signal(true));
+            *SIGNALS.lock() = Some((name, count, shown));
+            let child = match shown.get() {
+                true => run_scope(
+                    || {
+                        let clicks = create_signal(0);
+                        *CHILD.lock() = Some((get_current_scope().unwrap(), clicks));
+                        Node::Text(format!("clicks: {}", clicks.get()))
+                    },
+                    |_| {},
+                ),
+                false => Node::Empty,
+            };
+            rsx!(<p>{format!("{}: {}", name.get(), count.get())}{child}</p>)
+        }
+
+        // a session of the same app, in a runtime where other state took the
+        // first slots
+        let runtime = Runtime::new();
+        let (_state, _) = runtime.run(|| create_root(|| (create_signal(1), create_signal(2))));
+        runtime.run(|| run_scope(app, |_| {}));
+        let (name, count, shown) = SIGNALS.lock().unwrap();
+        let (child, clicks) = CHILD.lock().unwrap();
+        runtime.start_recording();
+        runtime.run(|| {
+            record_event("click", Some(child));
+            clicks.set(1);
+            flush();
+            // the child goes along with its signal, and comes back with new ones
+            shown.set(false);
+            flush();
+            shown.set(true);
+            flush();
+            let (_, clicks) = CHILD.lock().unwrap();
+            clicks.set(3);
+            batch(|| {
+                name.set("grace\nhopper");
+                count.update(|count| *count += 2);
+            });
+        });
+        let recording = runtime.stop_recording();
+        assert_eq!(recording.entries.len(), 7);
+        // the child that replaced the clicked one sits at the same path
+        let (new_child, _) = CHILD.lock().unwrap();
+        assert_ne!(new_child, child);
+        let child_path = runtime.run(|| scope_path(new_child));
+        assert!(child_path.is_some());
+        assert!(matches!(
+            &recording.entries[0],
+            RecordedEntry::Event { scope, name, .. } if *scope == child_path && name == "click"
+        ));
+        let signal = |index: usize| match &recording.entries[index] {
+            RecordedEntry::Set { signal, .. } => signal.clone(),
+            _ => panic!("expected a change"),
+        };
+        // the new child has its signal at the same place as the old one
+        assert_eq!(signal(1), signal(4));
+        assert!(matches!(
+            &recording.entries[6],
+            RecordedEntry::Set { signal, value: Some(value), .. }
+                if *signal == runtime.run(|| count.id()) && value == "2"
+        ));
+
+        let parsed = Recording::parse(&recording.to_string()).unwrap();
+        assert_eq!(parsed, recording);
+
+        // replaying a string again reuses the one decoded first
+        let decoded = <&'static str>::decode("grace\nhopper").unwrap();
+        assert!(core::ptr::eq(
+            decoded,
+            <&'static str>::decode("grace\nhopper").unwrap()
+        ));
+
+        let mut rendered = Vec::new();
+        replay(&parsed, app, |_, node| rendered.push(node.to_string())).unwrap();
+        assert_eq!(
+            rendered,
+            [
+                "<p>ada: 0clicks: 0</p>",
+                "<p>ada: 0clicks: 1</p>",
+                "<p>ada: 0</p>",
+                "<p>ada: 0clicks: 0</p>",
+                "<p>ada: 0clicks: 3</p>",
+                // rendering the parent again brings up a new child, as live
+                "<p>grace\nhopper: 0clicks: 0</p>",
+                "<p>grace\nhopper: 2clicks: 0</p>",
+            ]
+        );
+
+        // values of types that aren't registered can't be replayed
+        let name = runtime.run(|| name.id());
+        let unknown = Recording {
+            entries: vec![RecordedEntry::Set {
+                scope: None,
+                signal: name.clone(),
+                value: None,
+                at_ms: None,
+            }],
+        };
+        assert_eq!(
+            replay(&unknown, app, |_, _| {}),
+            Err(ReplayError::MissingValue(name))
         );
     }
 }
@@ -264,4 +264,50 @@ mod tests {
         let rsx = rsx!(<button {disabled} />); // notice how we don't need to use assignment?
         assert_eq!(rsx.to_string(), "<button disabled=\"true\"></button>")
     }
+
+    #[test]
+    fn test_rsx_dynamic_bindings() {
+        let name = "World";
+        let rsx = rsx!(<p title={move || name}>{move || name}</p>);
+        assert_eq!(rsx.to_string(), "<p title=\"World\">World</p>");
+
+        let rsx = rsx!(<p title={move || ""}>{move || when!(false => <b />)}</p>);
+        assert_eq!(rsx.to_string(), "<p></p>");
+    }
+
+    #[test]
+    fn test_runtimes_render_in_parallel() {
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                std::thread::spawn(move || {
+                    Runtime::new().run(|| {
+                        let (_root, count) = create_root(|| create_signal(thread));
+                        for value in 0..100 {
+                            count.set(thread * 1000 + value);
+                        }
+                        rsx!(<p>{count}</p>).to_string()
+                    })
+                })
+            })
+            .collect();
+        for (thread, handle) in threads.into_iter().enumerate() {
+            assert_eq!(
+                handle.join().unwrap(),
+                format!("<p>{}</p>", thread * 1000 + 99)
+            );
+        }
+    }
+
+    #[test]
+    fn test_local_signals_stay_on_their_thread() {
+        use momenta::signals::SignalError;
+        use std::rc::Rc;
+
+        let (_root, shared) = create_root(|| create_local_signal(Rc::new(1)));
+        // the handle crosses threads, but the value is only reached through the
+        // runtime of the thread that created it
+        let other = std::thread::spawn(move || shared.try_with(Rc::strong_count));
+        assert_eq!(other.join().unwrap(), Err(SignalError::MissingRuntime));
+        assert_eq!(shared.with(|value| **value), Some(1));
+    }
 }